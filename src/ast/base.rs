@@ -1,7 +1,8 @@
+use std::cmp::{max, min};
 use crate::core::IdString;
 use crate::ast::Expression;
 
-#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
 pub struct LineCol {
 	pub line: u32,
 	pub col: u32,
@@ -11,6 +12,8 @@ pub struct LineCol {
 pub struct SrcInfo {
 	pub file: IdString,
 	pub start: LineCol,
+	// Just past the last character
+	pub end: LineCol,
 }
 
 impl SrcInfo {
@@ -18,6 +21,21 @@ impl SrcInfo {
 		SrcInfo {
 			file: IdString::NONE,
 			start: LineCol { line: 0, col: 0},
+			end: LineCol { line: 0, col: 0},
+		}
+	}
+	// The smallest span covering both; unknown locations are ignored
+	pub fn join(&self, other: &SrcInfo) -> SrcInfo {
+		if self.file == IdString::NONE {
+			*other
+		} else if other.file != self.file {
+			*self
+		} else {
+			SrcInfo {
+				file: self.file,
+				start: min(self.start, other.start),
+				end: max(self.end, other.end),
+			}
 		}
 	}
 }
@@ -159,7 +159,12 @@ impl fmt::Display for TemplateArg {
 pub struct StructureDef {
 	pub name: IdString,
 	pub is_interface: bool,
+	pub is_union: bool,
+	// Types inherited from, whose members come before those of this structure
+	pub bases: Vec<DataType>,
 	pub templ_args: Vec<TemplateArg>,
+	// The template arguments this is a specialisation for, if it is one
+	pub specialisation: Option<Vec<TemplateValue>>,
 	pub block: Box<Statement>,
 	pub attrs: AttributeList,
 	pub src: SrcInfo,
@@ -51,10 +51,10 @@ macro_rules! operators {
 }
 
 operators! {
-	("++",  PostInc,   2, 16, true,  false),
-	("--",  PostDec,   2, 16, true,  false),
-	("++",  PreInc,    2, 15, false, true),
-	("--",  PreDec,    2, 15, false, true),
+	("++",  PostInc,   1, 16, true,  false),
+	("--",  PostDec,   1, 16, true,  false),
+	("++",  PreInc,    1, 15, false, true),
+	("--",  PreDec,    1, 15, false, true),
 	("+",   Promote,   1, 15, false, true),
 	("-",   Negate,    1, 15, false, true),
 	("!",   LogNot,    1, 15, false, true),
@@ -80,6 +80,7 @@ operators! {
 	("|",   BwOr,      2,  5, false, false),
 	("&&",  LogAnd,    2,  4, false, false),
 	("||",  LogOr,     2,  3, false, false),
+	("?",   Ternary,   3,  2, false, true),
 	("=",   Assign,    2,  2, false, true),
 	("+=",  AsAdd,     2,  2, false, true),
 	("-=",  AsSub,     2,  2, false, true),
@@ -113,6 +114,12 @@ pub struct BitSlice {
 	pub end: Box<Expression>,
 }
 
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Replication {
+	pub count: Box<Expression>,
+	pub value: Box<Expression>,
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub enum BuiltinType {
 	SizeOf,
@@ -126,6 +133,7 @@ pub enum BuiltinType {
 pub enum ExprType {
 	Null,
 	Literal(BitVector),
+	StrLiteral(String),
 	Variable(IdString),
 	MemberAccess(Box<Expression>, IdString),
 	TemplateArg(IdString),
@@ -134,16 +142,25 @@ pub enum ExprType {
 	Func(FuncCall),
 	ArrAcc(ArrayAccess),
 	Slice(BitSlice),
+	Cat(Vec<Expression>),
+	Rep(Replication),
 	Builtin(BuiltinType),
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
+#[derive(Eq, Debug, Clone)]
 pub struct Expression {
 	pub ty: ExprType,
 	pub attrs: AttributeList,
 	pub src: SrcInfo,
 }
 
+// Where an expression was written doesn't change what it means
+impl PartialEq for Expression {
+	fn eq(&self, other: &Expression) -> bool {
+		self.ty == other.ty && self.attrs == other.attrs
+	}
+}
+
 impl Expression {
 	pub fn new(ty: ExprType)  -> Expression {
 		Expression {
@@ -182,6 +199,7 @@ impl fmt::Display for Expression {
 					write!(f, "{}", l)?
 				}
 			},
+			StrLiteral(s) => write!(f, "{:?}", s)?,
 			Variable(v) => write!(f, "{:?}", v)?,
 			MemberAccess(base, m) => write!(f, "{}.{:?}", base, m)?,
 			TemplateArg(t) => write!(f, "{:?}", t)?,
@@ -195,6 +213,7 @@ impl fmt::Display for Expression {
 				write!(f, "({})", exprs[0])?;
 				if o.is_postfix() || exprs.len() > 1 { write!(f, "{}", o.token())?; }
 				if exprs.len() == 2 { write!(f, "({})", exprs[1])?; }
+				if exprs.len() == 3 { write!(f, "({}):({})", exprs[1], exprs[2])?; }
 			},
 			Func(fc) => {
 				write!(f, "{}(", fc.target)?;
@@ -209,6 +228,12 @@ impl fmt::Display for Expression {
 				write!(f, "]")?;
 			},
 			Slice(s) => write!(f, "{}[{}:{}]", s.array, s.start, s.end)?,
+			Cat(exprs) => {
+				write!(f, "cat(")?;
+				for e in exprs.iter() { write!(f, "{},", e)?; }
+				write!(f, ")")?;
+			},
+			Rep(r) => write!(f, "rep<{}>({})", r.count, r.value)?,
 			Builtin(_) => { unimplemented!() } 
 		};
 		Ok(())
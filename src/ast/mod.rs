@@ -7,7 +7,7 @@ pub mod namespace;
 
 pub use base::{LineCol, SrcInfo, Attribute, AttributeList};
 pub use datatype::{IntegerType, UserType, TemplateValue, ArrayType, DataTypes, DataType, TemplateArg, TemplateArgType, StructureDef};
-pub use expression::{Operator, FuncCall, ExprType, ArrayAccess, Expression};
-pub use statement::{VariableDecl, TypedefDecl, UsingDecl, IfStatement, ForLoop, StatementType, Statement, Module, ModuleIO, IODir, Function, FunctionArg};
-pub use namespace::Namespace;
+pub use expression::{Operator, FuncCall, ExprType, ArrayAccess, Replication, Expression};
+pub use statement::{VariableDecl, TypedefDecl, UsingDecl, IfStatement, ForLoop, RangeForLoop, WhileLoop, SwitchStatement, StatementType, Statement, Module, ModuleIO, IODir, Function, FunctionArg};
+pub use namespace::{Namespace, NamespaceItem};
 pub use scope::{IdentifierType, ScopeLevel, NullEntry, StructHeaderEntry};
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::core::IdString;
 use crate::ast::base::*;
 use crate::ast::{Statement};
@@ -23,4 +25,31 @@ impl Namespace {
 			src: src,
 		}
 	}
+	// The statements directly inside this namespace, excluding nested namespaces
+	pub fn statements(&self) -> Vec<&Statement> {
+		self.content.iter().filter_map(|item| match item {
+			NamespaceItem::Stmt(st) => Some(st),
+			_ => None,
+		}).collect()
+	}
+	pub fn dump<T: std::fmt::Write>(&self, stream: &mut T, indent: usize) -> fmt::Result {
+		for item in self.content.iter() {
+			match item {
+				NamespaceItem::Namespace(ns) => {
+					writeln!(stream, "{:indent$}namespace {:?} {{", "", ns.name.unwrap(), indent=indent)?;
+					ns.dump(stream, indent + 2)?;
+					writeln!(stream, "{:indent$}}}", "", indent=indent)?;
+				},
+				NamespaceItem::Stmt(st) => st.dump(stream, indent, true)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Display for Namespace {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.dump(f, 0)?;
+		Ok(())
+	}
 }
@@ -1,9 +1,13 @@
-use crate::ast::Statement;
+use crate::ast::{Statement, TemplateArg, TemplateArgType};
 use crate::core::IdString;
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum IdentifierType {
 	Type,
 	Var,
+	// Functions with template parameters, which may be followed by explicit template arguments
+	TemplateFunc,
+	Namespace,
 }
 
 // Various different kinds of scopes; from the parser point of view
@@ -33,8 +37,23 @@ impl ScopeEntry for Vec<Statement> {
 	fn is_var(&self, id: IdString) -> bool { self.iter().any(|st| st.leaf_is_var(id)) }
 }
 
+// Template parameters are visible inside the templated declaration
+impl ScopeEntry for Vec<TemplateArg> {
+	fn is_type(&self, id: IdString) -> bool { self.iter().any(|a| a.name == id && matches!(a.arg_type, TemplateArgType::Typename{..})) }
+	fn is_var(&self, id: IdString) -> bool { self.iter().any(|a| a.name == id && matches!(a.arg_type, TemplateArgType::Value{..})) }
+}
 
 pub struct ScopeLevel<'a> {
 	pub parent: Option<&'a ScopeLevel<'a>>,
 	pub entry: &'a dyn ScopeEntry,
+}
+
+impl <'a> ScopeLevel<'a> {
+	// Recursive versions, searching all parent scopes too
+	pub fn is_type(&self, id: IdString) -> bool {
+		self.entry.is_type(id) || self.parent.map(|p| p.is_type(id)).unwrap_or(false)
+	}
+	pub fn is_var(&self, id: IdString) -> bool {
+		self.entry.is_var(id) || self.parent.map(|p| p.is_var(id)).unwrap_or(false)
+	}
 }
\ No newline at end of file
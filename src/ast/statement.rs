@@ -2,7 +2,7 @@ use std::fmt;
 
 use crate::core::IdString;
 use crate::ast::base::*;
-use crate::ast::{DataType, Expression, StructureDef, TemplateArg};
+use crate::ast::{DataType, Expression, StructureDef, TemplateArg, TemplateValue};
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct VariableDecl {
@@ -31,6 +31,16 @@ pub struct IfStatement {
 	pub is_meta: bool,
 }
 
+// for (type name : range) body
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct RangeForLoop {
+	pub var_name: IdString,
+	pub var_type: DataType,
+	pub range: Expression,
+	pub body: Box<Statement>,
+	pub is_meta: bool,
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct ForLoop {
 	pub init: Box<Statement>,
@@ -40,6 +50,21 @@ pub struct ForLoop {
 	pub is_meta: bool,
 }
 
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct WhileLoop {
+	pub cond: Expression,
+	pub body: Box<Statement>,
+	pub is_meta: bool,
+	pub is_do_while: bool,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct SwitchStatement {
+	pub value: Expression,
+	// A block containing case labels, and the statements following them
+	pub body: Box<Statement>,
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct MulticycleBlock {
 	pub content: Box<Statement>,
@@ -57,8 +82,11 @@ pub struct FunctionArg {
 pub struct Function {
 	pub name: IdString,
 	pub templ_args: Vec<TemplateArg>,
+	pub specialisation: Option<Vec<TemplateValue>>,
 	pub func_args: Vec<FunctionArg>,
 	pub ret_type: DataType,
+	// Const member functions may not modify the object they are called on
+	pub is_const: bool,
 	pub attrs: AttributeList,
 	pub src: SrcInfo,
 	pub content: Box<Statement>,
@@ -81,6 +109,7 @@ pub struct ModuleIO {
 pub struct Module {
 	pub name: IdString,
 	pub templ_args: Vec<TemplateArg>,
+	pub specialisation: Option<Vec<TemplateValue>>,
 	pub ports: Vec<ModuleIO>,
 	pub attrs: AttributeList,
 	pub src: SrcInfo,
@@ -93,13 +122,21 @@ pub enum StatementType {
 	Typedef(TypedefDecl),
 	Using(UsingDecl),
 	Var(VariableDecl),
+	VarList(Vec<VariableDecl>),
 	If(IfStatement),
 	For(ForLoop),
+	RangeFor(RangeForLoop),
+	While(WhileLoop),
+	Switch(SwitchStatement),
+	Case(Expression),
+	Default,
 	Block(Vec<Statement>),
 	Multicycle(MulticycleBlock),
 	Return(Expression),
 	Break,
 	Continue,
+	// an assertion checked at elaboration time, and its message
+	StaticAssert(Expression, Option<String>),
 	Module(Module),
 	Func(Function),
 	Struct(StructureDef),
@@ -111,6 +148,8 @@ pub enum StatementType {
 pub struct Statement {
 	pub ty: StatementType,
 	pub attrs: AttributeList,
+	// From any /// comments before the statement
+	pub doc: Option<String>,
 	pub src: SrcInfo,
 }
 
@@ -121,6 +160,7 @@ impl Statement {
 		Statement {
 			ty: ty,
 			attrs: attrs,
+			doc: None,
 			src: SrcInfo::default(),
 		}
 	}
@@ -128,6 +168,9 @@ impl Statement {
 		match &self.ty {
 			If(i) => if i.if_false.is_some() { 2 } else { 1 },
 			For(_) => 2,
+			RangeFor(_) => 1,
+			While(_) => 1,
+			Switch(_) => 1,
 			Block(s) => s.len(),
 			Multicycle(_) => 1,
 			Func(_) => 1,
@@ -148,6 +191,9 @@ impl Statement {
 				1 => { return &s.body },
 				_ => {}
 			}
+			RangeFor(s) => if i == 0 { return &s.body },
+			While(s) => if i == 0 { return &s.body },
+			Switch(s) => if i == 0 { return &s.body },
 			Block(s) => { return s.get(i).unwrap() },
 			Multicycle(s) => if i == 0 { return &s.content },
 			Module(s) => if i == 0 { return &s.content },
@@ -177,6 +223,7 @@ impl Statement {
 	pub fn leaf_is_var(&self, ident: IdString) -> bool {
 		match &self.ty {
 			Var(s) => s.name == ident,
+			VarList(vs) => vs.iter().any(|s| s.name == ident),
 			Func(s) => s.name == ident,
 			_ => false,
 		}
@@ -199,7 +246,21 @@ impl Statement {
 		Ok(())
 	}
 
+	fn write_spec<T: std::fmt::Write>(stream: &mut T, spec: &Option<Vec<TemplateValue>>) -> fmt::Result {
+		if let Some(vals) = spec {
+			write!(stream, "<")?;
+			for v in vals.iter() {
+				write!(stream, "{:?}, ", v)?;
+			}
+			write!(stream, ">")?;
+		}
+		Ok(())
+	}
+
 	pub fn dump<T: std::fmt::Write>(&self, stream: &mut T, indent: usize, newline: bool) -> fmt::Result {
+		for line in self.doc.iter().flat_map(|d| d.lines()) {
+			writeln!(stream, "{:indent$}/// {}", "", line, indent=indent)?;
+		}
 		write!(stream, "{:indent$}", "", indent=indent)?;
 		use StatementType::*;
 		match &self.ty {
@@ -211,6 +272,14 @@ impl Statement {
 				if let Some(i) = &v.init { write!(stream, " = {}", i)?; };
 				write!(stream, ";")?;
 			},
+			VarList(vs) => {
+				for (j, v) in vs.iter().enumerate() {
+					if j > 0 { write!(stream, ", ")?; }
+					write!(stream, "{} {:?}", v.ty, v.name)?;
+					if let Some(i) = &v.init { write!(stream, " = {}", i)?; };
+				}
+				write!(stream, ";")?;
+			},
 			If(i) => {
 				writeln!(stream, "if {}({})", if i.is_meta {"meta "} else {""}, i.cond)?;
 				i.if_true.dump(stream, indent + 2, true)?;
@@ -225,6 +294,26 @@ impl Statement {
 				writeln!(stream, "{};{})", f.cond, f.incr)?;
 				f.body.dump(stream, indent + 2, true)?;
 			},
+			RangeFor(f) => {
+				writeln!(stream, "for {}({} {:?} : {})", if f.is_meta {"meta "} else {""}, f.var_type, f.var_name, f.range)?;
+				f.body.dump(stream, indent + 2, true)?;
+			},
+			While(w) => {
+				if w.is_do_while {
+					writeln!(stream, "do {}", if w.is_meta {"meta "} else {""})?;
+					w.body.dump(stream, indent + 2, true)?;
+					write!(stream, "{:indent$}while ({});", "", w.cond, indent=indent)?;
+				} else {
+					writeln!(stream, "while {}({})", if w.is_meta {"meta "} else {""}, w.cond)?;
+					w.body.dump(stream, indent + 2, true)?;
+				}
+			},
+			Switch(s) => {
+				writeln!(stream, "switch ({})", s.value)?;
+				s.body.dump(stream, indent + 2, true)?;
+			},
+			Case(e) => write!(stream, "case {}:", e)?,
+			Default => write!(stream, "default:")?,
 			Block(b) => {
 				writeln!(stream, "{{")?;
 				for s in b.iter() { s.dump(stream, indent + 2, true)?; }
@@ -233,9 +322,15 @@ impl Statement {
 			Return(e) => write!(stream, "return {};", e)?,
 			Break => write!(stream, "break;")?,
 			Continue => write!(stream, "continue;")?,
+			StaticAssert(e, msg) => match msg {
+				Some(m) => write!(stream, "static_assert({}, {:?});", e, m)?,
+				None => write!(stream, "static_assert({});", e)?,
+			},
 			Module(m) => {
 				Self::write_targs(stream, &m.templ_args)?;
-				write!(stream, "module {:?}(", m.name)?;
+				write!(stream, "module {:?}", m.name)?;
+				Self::write_spec(stream, &m.specialisation)?;
+				write!(stream, "(")?;
 				for p in m.ports.iter().filter(|p| p.dir == IODir::Input) {
 					write!(stream, "{} {:?},", p.arg_type, p.name)?;
 				}
@@ -248,23 +343,32 @@ impl Statement {
 			},
 			Func(f) => {
 				Self::write_targs(stream, &f.templ_args)?;
-				write!(stream, "{} {:?}(", f.ret_type, f.name)?;
+				write!(stream, "{} {:?}", f.ret_type, f.name)?;
+				Self::write_spec(stream, &f.specialisation)?;
+				write!(stream, "(")?;
 				for a in f.func_args.iter() {
 					write!(stream, "{} {:?}", a.data_type, a.name)?;
 					if let Some(d) = &a.default { write!(stream, " = {}", d)?; }
 					write!(stream, ",")?;
 				}
 				write!(stream, ")")?;
+				if f.is_const { write!(stream, " const")?; }
 				f.content.dump(stream, indent + 2, true)?;
 			},
 			Struct(s) => {
 				Self::write_targs(stream, &s.templ_args)?;
 				if s.is_interface {
 					write!(stream, "inteface ")?
+				} else if s.is_union {
+					write!(stream, "union ")?;
 				} else {
 					write!(stream, "struct ")?;
 				}
 				write!(stream, "{:?}", s.name)?;
+				Self::write_spec(stream, &s.specialisation)?;
+				for (i, b) in s.bases.iter().enumerate() {
+					write!(stream, "{} {}", if i == 0 { " :" } else { "," }, b)?;
+				}
 				s.block.dump(stream, indent + 2, true)?;
 			},
 			Expr(e) => write!(stream, "{}", e)?,
@@ -1,4 +1,5 @@
 use rustc_hash::FxHashMap;
+use crate::ast::SrcInfo;
 use crate::core::{IdString, BitVector, BasicOp, OperandType, Constant};
 use crate::design::PortDir;
 
@@ -17,7 +18,7 @@ pub enum LowPrim {
 	Const {val: BitVector},
 	Assign {src: IdString},
 	BitSelect {src: IdString, start: usize, end: usize},
-	Cat {src: Vec<IdString>},
+	Cat {src: Vec<IdString>}, // least significant first
 	Mux {src: Vec<IdString>, sel: IdString},
 }
 
@@ -27,9 +28,25 @@ pub struct LowNode {
 	pub value: LowPrim,
 	pub attrs: FxHashMap<IdString, Constant>,
 	pub typ: OperandType,
+	pub src: SrcInfo,
+}
+
+// Formal properties, which don't drive a node
+pub enum LowPropertyKind {
+	Assert,
+	Assume,
+}
+
+pub struct LowProperty {
+	pub name: IdString,
+	pub kind: LowPropertyKind,
+	pub a: IdString,
+	pub en: IdString,
+	pub src: SrcInfo,
 }
 
 pub struct LowNetlist {
 	pub name: IdString,
 	pub nodes: FxHashMap<IdString, LowNode>,
+	pub props: Vec<LowProperty>,
 }
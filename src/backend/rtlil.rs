@@ -1,22 +1,59 @@
-use crate::core::{IdStringDb, IdString, BasicOp};
+use crate::ast::SrcInfo;
+use crate::core::{IdStringDb, IdString, BasicOp, BitVector, Constant};
 use crate::design::PortDir;
 use crate::backend::low_netlist::*;
 
 use std::io::{Write, Result};
 use std::fs::File;
 
-struct RTLILBackend<'a> {
+// A sized constant, most significant bit first
+fn rtlil_bits(bits: &BitVector) -> String {
+	format!("{}'{}", bits.len(), bits.to_str())
+}
+
+// A constant in RTLIL syntax; strings are escaped in the same way as Yosys writes them
+fn rtlil_const(value: &Constant) -> String {
+	match value {
+		Constant::Bits(b) => rtlil_bits(b),
+		Constant::Str(s) => {
+			let mut result = String::from("\"");
+			for c in s.chars() {
+				match c {
+					'\n' => result.push_str("\\n"),
+					'\t' => result.push_str("\\t"),
+					'"' => result.push_str("\\\""),
+					'\\' => result.push_str("\\\\"),
+					c if (c as u32) < 32 => result.push_str(&format!("\\{:03o}", c as u32)),
+					c => result.push(c),
+				}
+			}
+			result.push('"');
+			result
+		},
+		Constant::Enum(_, v) => format!("{}", v),
+	}
+}
+
+struct RTLILBackend<'a, W: Write> {
 	nl: LowNetlist,
-	f: File,
+	f: W,
 	ids: &'a IdStringDb,
 }
 
-impl <'a> RTLILBackend<'a> {
+impl <'a> RTLILBackend<'a, File> {
 	pub fn run(nl: LowNetlist, filename: &str, ids: &IdStringDb) -> Result<()> {
-		let mut ctx = RTLILBackend {nl: nl, f: File::create(filename)?, ids: ids};
-		ctx.write_design()?;
+		RTLILBackend::write_to(nl, File::create(filename)?, ids)?;
 		Ok(())
 	}
+}
+
+impl <'a, W: Write> RTLILBackend<'a, W> {
+	// Write a netlist to any output, giving the output back
+	pub fn write_to(nl: LowNetlist, f: W, ids: &IdStringDb) -> Result<W> {
+		let mut ctx = RTLILBackend {nl: nl, f: f, ids: ids};
+		ctx.write_design()?;
+		Ok(ctx.f)
+	}
 	fn s(&self, id: IdString) -> String {
 		let st = id.str(self.ids);
 		if st.chars().next() == Some('$') {
@@ -25,9 +62,20 @@ impl <'a> RTLILBackend<'a> {
 			format!("\\{}", st)
 		}
 	}
+	// Where something came from, in the same file:line.col-line.col form as Yosys uses
+	fn src_attr(&self, src: SrcInfo) -> Option<String> {
+		if src.file == IdString::NONE {
+			return None;
+		}
+		Some(format!("attribute \\src \"{}:{}.{}-{}.{}\"", src.file.str(self.ids), src.start.line, src.start.col, src.end.line, src.end.col))
+	}
 	fn write_wires(&mut self) -> Result<()> {
 		let mut port_idx = 0;
 		for (name, data) in self.nl.nodes.iter() {
+			for (attr, value) in data.attrs.iter() {
+				writeln!(self.f, "attribute {} {}", self.s(*attr), rtlil_const(value))?;
+			}
+			if let Some(attr) = self.src_attr(data.src) { writeln!(self.f, "{}", attr)?; }
 			write!(self.f, "wire width {} ", data.typ.width)?;
 			if let Some(dir) = data.dir {
 				match dir {
@@ -57,6 +105,10 @@ impl <'a> RTLILBackend<'a> {
 			match &data.value {
 				LowPrim::Null | LowPrim::Input => {},
 				LowPrim::Op {ty, a, b} => {
+					for (attr, value) in data.attrs.iter() {
+						writeln!(self.f, "  attribute {} {}", self.s(*attr), rtlil_const(value))?;
+					}
+					if let Some(attr) = self.src_attr(data.src) { writeln!(self.f, "  {}", attr)?; }
 					writeln!(self.f, "  cell {} {}_op", self.get_yosys_cell(*ty, data.typ.is_signed), self.s(*name))?;
 					let a_node = self.nl.nodes.get(a).unwrap();
 					writeln!(self.f, "     parameter \\A_SIGNED {}", a_node.typ.is_signed)?;
@@ -72,11 +124,28 @@ impl <'a> RTLILBackend<'a> {
 					writeln!(self.f, "     connect \\Y {}", self.s(*name))?;
 				},
 				LowPrim::Const { val } => {
-					writeln!(self.f, "  connect {} {}", self.s(*name), val.to_string())?;
+					writeln!(self.f, "  connect {} {}", self.s(*name), rtlil_bits(val))?;
+				},
+				LowPrim::Cat { src } => {
+					// RTLIL concatenations are most significant first
+					write!(self.f, "  connect {} {{", self.s(*name))?;
+					for s in src.iter().rev() { write!(self.f, " {}", self.s(*s))?; }
+					writeln!(self.f, " }}")?;
 				},
 				_ => unimplemented!(),
 			}
 		}
+		for prop in self.nl.props.iter() {
+			let cell = match prop.kind {
+				LowPropertyKind::Assert => "$assert",
+				LowPropertyKind::Assume => "$assume",
+			};
+			if let Some(attr) = self.src_attr(prop.src) { writeln!(self.f, "  {}", attr)?; }
+			writeln!(self.f, "  cell {} {}", cell, self.s(prop.name))?;
+			writeln!(self.f, "     connect \\A {}", self.s(prop.a))?;
+			writeln!(self.f, "     connect \\EN {}", self.s(prop.en))?;
+			writeln!(self.f, "  end")?;
+		}
 		Ok(())
 	}
 
@@ -87,4 +156,65 @@ impl <'a> RTLILBackend<'a> {
 		writeln!(self.f, "end")?;
 		Ok(())
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rustc_hash::FxHashMap;
+	use crate::core::{OperandType, constids};
+
+	fn node(name: IdString, value: LowPrim, width: usize) -> LowNode {
+		LowNode { name: name, dir: None, value: value, attrs: FxHashMap::default(), typ: OperandType::unsigned(width), src: SrcInfo::default() }
+	}
+
+	fn emit(nl: LowNetlist, ids: &IdStringDb) -> String {
+		String::from_utf8(RTLILBackend::write_to(nl, Vec::new(), ids).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn properties() {
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let (a, en, p) = (ids.id("a"), ids.id("en"), ids.id("$assert$1$"));
+		let mut nodes = FxHashMap::default();
+		nodes.insert(a, node(a, LowPrim::Input, 1));
+		nodes.insert(en, node(en, LowPrim::Input, 1));
+		let props = vec![LowProperty { name: p, kind: LowPropertyKind::Assert, a: a, en: en, src: SrcInfo::default() }];
+		let text = emit(LowNetlist { name: ids.id("top"), nodes: nodes, props: props }, &ids);
+		assert!(text.contains("  cell $assert $assert$1$\n     connect \\A \\a\n     connect \\EN \\en\n  end\n"));
+	}
+
+	#[test]
+	fn constants() {
+		assert_eq!(rtlil_const(&Constant::Bits(BitVector::from_u64(1, 8))), "8'00000001");
+		assert_eq!(rtlil_const(&Constant::Bits(BitVector::from_str("1x0z"))), "4'1x0z");
+		assert_eq!(rtlil_const(&Constant::Str("block".to_string())), "\"block\"");
+		assert_eq!(rtlil_const(&Constant::Str("say \"hi\"\\\n\t\x01".to_string())), "\"say \\\"hi\\\"\\\\\\n\\t\\001\"");
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let (a, k) = (ids.id("a"), ids.id("keep"));
+		let mut nodes = FxHashMap::default();
+		let mut n = node(a, LowPrim::Const { val: BitVector::from_u64(5, 4) }, 4);
+		n.attrs.insert(k, Constant::Bits(BitVector::from_u64(1, 1)));
+		n.attrs.insert(constids::doc, Constant::Str("a \"quoted\" doc".to_string()));
+		nodes.insert(a, n);
+		let text = emit(LowNetlist { name: ids.id("top"), nodes: nodes, props: Vec::new() }, &ids);
+		assert!(text.contains("attribute \\keep 1'1\n"));
+		assert!(text.contains("attribute \\doc \"a \\\"quoted\\\" doc\"\n"));
+		assert!(text.contains("  connect \\a 4'0101\n"));
+	}
+
+	#[test]
+	fn cat() {
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let (lo, hi, y) = (ids.id("lo"), ids.id("hi"), ids.id("y"));
+		let mut nodes = FxHashMap::default();
+		nodes.insert(lo, node(lo, LowPrim::Input, 4));
+		nodes.insert(hi, node(hi, LowPrim::Input, 2));
+		nodes.insert(y, node(y, LowPrim::Cat { src: vec![lo, hi] }, 6));
+		let text = emit(LowNetlist { name: ids.id("top"), nodes: nodes, props: Vec::new() }, &ids);
+		// the last source is the most significant, so comes first
+		assert!(text.contains("  connect \\y { \\hi \\lo }\n"));
+	}
+}
@@ -0,0 +1,89 @@
+use crate::core::Constant;
+
+// The values a known attribute can take
+pub enum AttrValue {
+	// No value, or 0/1
+	Flag,
+	// A positive integer
+	Count,
+	// One of a set of names
+	Name(&'static [&'static str]),
+}
+
+// Attributes that mean something to the compiler, which are checked where they are used. Anything else is passed
+// through to the backends untouched
+pub const KNOWN_ATTRS: &[(&str, AttrValue)] = &[
+	("keep", AttrValue::Flag),
+	("ram_style", AttrValue::Name(&["auto", "block", "distributed", "registers", "ultra"])),
+	("unroll", AttrValue::Count),
+	("pipeline", AttrValue::Count),
+	("max_fanout", AttrValue::Count),
+];
+
+// Number of single character insertions, deletions and substitutions to turn one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut prev: Vec<usize> = (0..=b.len()).collect();
+	for (i, ca) in a.chars().enumerate() {
+		let mut curr = vec![i + 1];
+		for (j, cb) in b.iter().enumerate() {
+			curr.push(*[prev[j + 1] + 1, curr[j] + 1, prev[j] + if ca == *cb { 0 } else { 1 }].iter().min().unwrap());
+		}
+		prev = curr;
+	}
+	prev[b.len()]
+}
+
+// Check the value of an attribute against the registry. Unknown attributes are passed through, unless they look like a
+// typo of a known one; that is, within an edit distance of a third of their length
+pub fn check_attr(name: &str, value: &Constant) -> Result<(), String> {
+	let kind = match KNOWN_ATTRS.iter().find(|(n, _)| *n == name) {
+		Some((_, kind)) => kind,
+		None => {
+			return match KNOWN_ATTRS.iter().find(|(n, _)| edit_distance(n, name) * 3 <= name.chars().count()) {
+				Some((n, _)) => Err(format!("unknown attribute '{}', did you mean '{}'?", name, n)),
+				None => Ok(()),
+			};
+		},
+	};
+	let valid = match (kind, value) {
+		(AttrValue::Flag, Constant::Bits(b)) => b.as_def_u64().map_or(false, |v| v <= 1),
+		(AttrValue::Count, Constant::Bits(b)) => b.as_def_u64().map_or(false, |v| v > 0),
+		(AttrValue::Name(names), Constant::Str(s)) => names.contains(&s.as_str()),
+		_ => false,
+	};
+	if valid {
+		Ok(())
+	} else {
+		let expected = match kind {
+			AttrValue::Flag => format!("no value, 0 or 1"),
+			AttrValue::Count => format!("a positive integer"),
+			AttrValue::Name(names) => format!("one of {}", names.join(", ")),
+		};
+		Err(format!("invalid value {:?} for attribute {}, expected {}", value, name, expected))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::core::BitVector;
+
+	#[test]
+	fn registry() {
+		let flag = Constant::Bits(BitVector::from_u64(1, 1));
+		assert_eq!(check_attr("keep", &flag), Ok(()));
+		assert_eq!(check_attr("unroll", &Constant::Bits(BitVector::from_u64(4, 64))), Ok(()));
+		assert_eq!(check_attr("ram_style", &Constant::Str("distributed".to_string())), Ok(()));
+		assert!(check_attr("max_fanout", &Constant::Bits(BitVector::from_u64(0, 64))).unwrap_err().contains("positive integer"));
+		assert!(check_attr("ram_style", &Constant::Str("bram".to_string())).unwrap_err().contains("one of"));
+		// likely typos of known attributes
+		assert_eq!(check_attr("ram_stlye", &Constant::Str("block".to_string())), Err(format!("unknown attribute 'ram_stlye', did you mean 'ram_style'?")));
+		assert_eq!(check_attr("kep", &flag), Err(format!("unknown attribute 'kep', did you mean 'keep'?")));
+		assert!(check_attr("pipelines", &flag).unwrap_err().contains("did you mean 'pipeline'"));
+		// passed through; short names need to be closer to be taken as a typo
+		assert_eq!(check_attr("src_loc", &Constant::Str("x".to_string())), Ok(()));
+		assert_eq!(check_attr("ep", &flag), Ok(()));
+		assert_eq!(check_attr("fanout", &Constant::Bits(BitVector::from_u64(4, 64))), Ok(()));
+	}
+}
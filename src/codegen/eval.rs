@@ -1,22 +1,81 @@
-use crate::{BasicOp, BitVector, IdStringDb, IdString};
-use crate::ast::{SrcInfo, Expression, Statement, Operator, IODir};
-use crate::core::constids;
+use crate::{BasicOp, BitVector, Constant, IdStringDb, IdString, OperandType};
+use crate::ast::{Attribute, AttributeList, Namespace, NamespaceItem, SrcInfo, DataType, DataTypes, ExprType, Expression, FuncCall, Function, Statement, VariableDecl, RangeForLoop, StatementType, Operator, IODir, TemplateArg, TemplateArgType, TemplateValue};
+use crate::core::{constids, StoreIndex};
 use crate::codegen::state::*;
-use crate::codegen::{ResolvedType, ResolvedTypes, LValue, RValue, Variable};
-use crate::design::{PortDir, PrimitiveType};
+use crate::codegen::attrs;
+use crate::codegen::{ResolvedArg, ResolvedType, ResolvedTypes, LValue, RValue, ValuePathItem, Variable};
+use crate::design::{Design, Node, PortDir, PrimitiveType};
+
+// Upper bound on loop unrolling, to catch loops that will never terminate
+pub const MAX_LOOP_ITERATIONS: usize = 65536;
+// Upper bound on iterations predicated on a runtime condition, unless raised by [[unroll=N]]; each one duplicates the
+// loop body in the netlist
+pub const MAX_RUNTIME_LOOP_ITERATIONS: usize = 256;
+// Upper bound on nested function calls, to catch unbounded recursion
+pub const MAX_CALL_DEPTH: usize = 64;
+
+// Tracking of break and continue for the loop or switch currently being elaborated
+pub struct LoopState {
+	// Index into the condition stack where the current iteration's body starts
+	pub cond_idx: usize,
+	// Whether the loop has not yet been broken out of
+	pub active: RValue,
+	// Whether the current iteration has not yet been broken out of or continued
+	pub iter_active: RValue,
+	pub is_meta: bool,
+	pub kind: LoopKind,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LoopKind {
+	Loop,
+	// Switch statements can be broken out of, but continue applies to the enclosing loop
+	Switch,
+	// The body of a called function, which is only exited by return
+	Function,
+}
 
 pub struct Eval <'a> {
 	pub st: GenState<'a>,
 	pub is_const: bool,
+	pub loops: Vec<LoopState>,
+	// The variable holding the result of the function being called, if it isn't void
+	pub ret_var: Option<StoreIndex<Variable>>,
+	// Number of function calls currently being elaborated
+	pub call_depth: usize,
+	// Template instantiations currently being elaborated, innermost last; reported by failing static assertions
+	pub instantiations: Vec<String>,
+	// Conditions of the runtime if branches currently being elaborated, outermost first
+	pub branch_conds: Vec<String>,
+	// Assignments made inside runtime if branches, and whether the value they replaced was (partly) undefined
+	pub branch_writes: Vec<(LValue, bool)>,
+	// Variables that were undefined and then only assigned on some branches of a runtime if; with the location of the
+	// if and the conditions under which they were assigned
+	pub partial_vars: Vec<(StoreIndex<Variable>, SrcInfo, Vec<String>)>,
+	pub warnings: Vec<CodegenWarning>,
+	// Variables holding defines, which take the place of global declarations of the same name
+	pub defines: Vec<StoreIndex<Variable>>,
+}
+
+// The part of a value that an assignment replaces; or the whole value where the path can't be followed statically
+fn path_value<'v>(value: &'v RValue, path: &[ValuePathItem]) -> &'v RValue {
+	match (path.first(), value) {
+		(Some(ValuePathItem::ConstIndex(i)), RValue::Array(vals)) if *i < vals.len() => path_value(&vals[*i], &path[1..]),
+		(Some(ValuePathItem::Member(m)), RValue::Structure(sv)) if sv.values.contains_key(m) => path_value(&sv.values[m], &path[1..]),
+		_ => value,
+	}
 }
 
 impl <'a> Eval<'a> {
 	pub fn op_value(&mut self, src: SrcInfo, op: BasicOp, args: &[Expression]) -> Result<RValue, CodegenError> {
+		let mut mapped_args = Vec::new();
+		for a in args.iter() { mapped_args.push(self.eval_rvalue(a)?); }
+		self.op_rvalues(src, op, &mapped_args)
+	}
+	pub fn op_rvalues(&mut self, src: SrcInfo, op: BasicOp, mapped_args: &[RValue]) -> Result<RValue, CodegenError> {
 		let mut types = Vec::new();
 		let mut const_vals = Vec::new();
 		let mut fully_const = true;
-		let mut mapped_args = Vec::new();
-		for a in args.iter() { mapped_args.push(self.eval_rvalue(a)?); }
 		for arg in mapped_args.iter() {
 			let val_type = arg.to_type(&self.st).unwrap(); // TODO: why could this ever not resolve?
 			if let ResolvedTypes::Integer(it) = val_type.typ {
@@ -33,11 +92,13 @@ impl <'a> Eval<'a> {
 		if fully_const {
 			// Constant fold
 			Ok(RValue::Constant(op.apply(&const_vals)))
+		} else if let Some(result) = self.simplify_logic(src, op, mapped_args)? {
+			Ok(result)
 		} else {
 			// Create a node
 			let res_type = op.result_type(&types);
 			let prim_name = self.st.des.auto_id(self.st.ids);
-			let prim = self.st.des.add_prim(prim_name, PrimitiveType::BasicOp(op), src).unwrap();
+			let prim = self.st.add_prim(prim_name, PrimitiveType::BasicOp(op), src);
 			let input_names = &[constids::A, constids::B];
 			for i in 0..mapped_args.len() {
 				let node = self.st.get_node(&mapped_args[i], src);
@@ -47,28 +108,250 @@ impl <'a> Eval<'a> {
 			Ok(RValue::from_node(self.st.des.add_node(node_name, res_type, src, prim, constids::Q).unwrap()))
 		}
 	}
+	// Logical operations where one side is constant can often be folded, which loops and predication rely on
+	fn simplify_logic(&mut self, src: SrcInfo, op: BasicOp, args: &[RValue]) -> Result<Option<RValue>, CodegenError> {
+		if op != BasicOp::LogAnd && op != BasicOp::LogOr {
+			return Ok(None);
+		}
+		for i in 0..2 {
+			if let RValue::Constant(c) = &args[i] {
+				let other = &args[1 - i];
+				let value = BasicOp::LogCast.apply(&[c.clone()]).as_bool();
+				return Ok(Some(match (op, value) {
+					(BasicOp::LogAnd, false) => RValue::Constant(BitVector::from_u64(0, 1)),
+					(BasicOp::LogOr, true) => RValue::Constant(BitVector::from_u64(1, 1)),
+					_ => {
+						// the result is just the other operand as a boolean
+						if self.st.scalar_type(other) == Some(OperandType::BOOL) {
+							other.clone()
+						} else {
+							self.op_rvalues(src, BasicOp::LogCast, &[other.clone()])?
+						}
+					}
+				}));
+			}
+		}
+		Ok(None)
+	}
 	pub fn assign(&mut self, src: SrcInfo, lv: LValue, rv: RValue) -> Result<RValue, CodegenError> {
-		self.st.assign_variable(lv.var, &lv.path, &rv, src);
+		self.note_write(&lv);
+		self.st.assign_variable(lv.var, &lv.path, &rv, src)?;
 		Ok(rv)
 	}
+	// Track assignments inside runtime if branches, to find variables that might be read while undefined
+	fn note_write(&mut self, lv: &LValue) {
+		if self.branch_conds.is_empty() {
+			// assigned as a whole, unconditionally
+			if lv.path.is_empty() {
+				self.unmark_partial(lv.var);
+			}
+		} else {
+			let undef = self.partial_vars.iter().any(|(v, _, _)| *v == lv.var) || path_value(&self.st.vars.get(lv.var).value, &lv.path).has_undef();
+			self.branch_writes.push((lv.clone(), undef));
+		}
+	}
+	fn unmark_partial(&mut self, var: StoreIndex<Variable>) -> Option<usize> {
+		let pos = self.partial_vars.iter().position(|(v, _, _)| *v == var)?;
+		self.partial_vars.remove(pos);
+		Some(pos)
+	}
+	// Warn if a variable that is only assigned under some conditions is read
+	fn note_read(&mut self, src: SrcInfo, var: StoreIndex<Variable>) -> bool {
+		let pos = match self.partial_vars.iter().position(|(v, _, _)| *v == var) {
+			Some(pos) => pos,
+			None => return false,
+		};
+		let (_, if_src, conds) = self.partial_vars.remove(pos);
+		let name = self.st.vars.get(var).name;
+		self.warnings.push(CodegenWarning(src, format!("{:?} may be read while undefined, as it is only assigned when {} (in the if at {}:{})",
+			name, conds.join(" && "), if_src.start.line, if_src.start.col)));
+		true
+	}
+	// After both branches of a runtime if, mark variables that were undefined and only assigned on one of them. Writes
+	// from index start are from the true branch, and from mid the false branch
+	fn merge_branch_writes(&mut self, src: SrcInfo, cond: &str, start: usize, mid: usize, vars_before: usize, mut partial_before: usize) {
+		let writes = if self.branch_conds.is_empty() {
+			self.branch_writes.split_off(start)
+		} else {
+			// enclosing ifs need these too
+			self.branch_writes[start..].to_vec()
+		};
+		let (if_true, if_false) = writes.split_at(mid - start);
+		let sides = [(if_true, if_false, format!("{}", cond)), (if_false, if_true, format!("!({})", cond))];
+		for (side, other, branch_cond) in sides.iter() {
+			for (i, (lv, undef)) in side.iter().enumerate() {
+				// only the first assignment in the branch matters, and variables declared inside the if are out of scope
+				if side[..i].iter().any(|(l, _)| l == lv) || lv.var.index() >= vars_before {
+					continue;
+				}
+				if other.iter().any(|(l, _)| l == lv) {
+					// assigned on both branches, so anything known about the variable before the if no longer applies
+					if lv.path.is_empty() && self.partial_vars.iter().position(|(v, _, _)| *v == lv.var).map_or(false, |p| p < partial_before) {
+						self.unmark_partial(lv.var);
+						partial_before -= 1;
+					}
+				} else if *undef {
+					if self.unmark_partial(lv.var).map_or(false, |p| p < partial_before) {
+						partial_before -= 1;
+					}
+					let mut conds = self.branch_conds.clone();
+					conds.push(branch_cond.clone());
+					self.partial_vars.push((lv.var, src, conds));
+				}
+			}
+		}
+	}
+	// Assign to an expression; which might be a concatenation, splitting the value between its parts
+	pub fn assign_expr(&mut self, src: SrcInfo, target: &Expression, rv: RValue) -> Result<RValue, CodegenError> {
+		if let ExprType::Cat(parts) = &target.ty {
+			let mut types = Vec::new();
+			for part in parts.iter() {
+				let typ = self.eval_rvalue(part)?.to_type(&self.st).unwrap();
+				let width = typ.pack(&self.st)?.width;
+				types.push((typ, width));
+			}
+			let total_width = types.iter().map(|(_, w)| w).sum();
+			let rv_type = rv.to_type(&self.st).ok_or_else(|| CodegenError(src, format!("unable to assign {:?} to concatenation", rv)))?;
+			let packed = self.st.pack_value(&rv, &rv_type, src)?;
+			let packed = self.st.convert_scalar(&packed, OperandType::unsigned(total_width), src);
+			// the last part is in the least significant bits
+			let mut offset = 0;
+			for (part, (typ, width)) in parts.iter().zip(types.iter()).rev() {
+				let bits = self.st.slice_get(&packed, offset, OperandType::unsigned(*width), src);
+				let value = self.st.unpack_value(&bits, typ, src)?;
+				self.assign_expr(src, part, value)?;
+				offset += width;
+			}
+			Ok(rv)
+		} else {
+			let lv = self.eval_lvalue(target)?;
+			self.assign(src, lv, rv)
+		}
+	}
+	// Concatenate the packed bits of values, the first value ending up in the most significant bits
+	pub fn concat_values(&mut self, src: SrcInfo, values: &[RValue]) -> Result<RValue, CodegenError> {
+		let mut result: Option<RValue> = None;
+		for v in values.iter() {
+			let typ = v.to_type(&self.st).ok_or_else(|| CodegenError(src, format!("unable to concatenate {:?}", v)))?;
+			let packed = self.st.pack_value(v, &typ, src)?;
+			result = Some(match result {
+				Some(hi) => self.st.concat(&hi, &packed, src),
+				None => packed,
+			});
+		}
+		result.ok_or_else(|| CodegenError(src, format!("concatenation requires at least one value")))
+	}
+	pub fn eval_index(&mut self, expr: &Expression) -> Result<usize, CodegenError> {
+		match self.eval_rvalue(expr)? {
+			RValue::Constant(c) => c.as_def_u64().map(|i| i as usize).ok_or_else(|| CodegenError(expr.src, format!("array index {:?} is undefined", c))),
+			v => Err(CodegenError(expr.src, format!("non-constant array index {:?} not yet supported", v))),
+		}
+	}
 	pub fn eval_oper(&mut self, src: SrcInfo, ty: Operator, args: &[Expression]) -> Result<RValue, CodegenError> {
 		use crate::ast::Operator::*;
+		let basic_op = match ty {
+			Negate => Some(BasicOp::Neg),
+			LogNot => Some(BasicOp::LogNot),
+			BitNot => Some(BasicOp::BwNot),
+			RedOr => Some(BasicOp::LogCast),
+			Mul | AsMul => Some(BasicOp::Mul),
+			Div | AsDiv => Some(BasicOp::Div),
+			Mod | AsMod => Some(BasicOp::Mod),
+			Add | AsAdd | PreInc | PostInc => Some(BasicOp::Add),
+			Sub | AsSub | PreDec | PostDec => Some(BasicOp::Sub),
+			Shl | AsShl => Some(BasicOp::Shl),
+			Shr | AsShr => Some(BasicOp::Shr),
+			Lt => Some(BasicOp::Lt),
+			Gt => Some(BasicOp::Gt),
+			LtEq => Some(BasicOp::LtEq),
+			GtEq => Some(BasicOp::GtEq),
+			Eq => Some(BasicOp::Eq),
+			Neq => Some(BasicOp::Neq),
+			BwAnd | AsAnd => Some(BasicOp::BwAnd),
+			BwXor | AsXor => Some(BasicOp::BwXor),
+			BwOr | AsOr => Some(BasicOp::BwOr),
+			LogAnd => Some(BasicOp::LogAnd),
+			LogOr => Some(BasicOp::LogOr),
+			_ => None,
+		};
 		match ty {
-			Add => self.op_value(src, BasicOp::Add, args),
+			Promote => self.eval_rvalue(&args[0]),
+			RedAnd => {
+				// all bits set is the same as no bits clear
+				let inv = self.op_value(src, BasicOp::BwNot, args)?;
+				self.op_rvalues(src, BasicOp::LogNot, &[inv])
+			},
+			RedXor => Err(CodegenError(src, format!("reduction xor is not yet supported"))),
+			Ternary => {
+				match self.eval_rvalue(&args[0])? {
+					RValue::Constant(c) => self.eval_rvalue(&args[if c.as_bool() { 1 } else { 2 }]),
+					RValue::Node(n) => {
+						// side effects of each branch only happen when it is selected
+						self.st.push_cond(n, false);
+						let if_true = self.eval_rvalue(&args[1])?;
+						self.st.pop_cond();
+						self.st.push_cond(n, true);
+						let if_false = self.eval_rvalue(&args[2])?;
+						self.st.pop_cond();
+						self.st.mux_value(n, &if_false, &if_true, src)
+					},
+					v => Err(CodegenError(src, format!("expected scalar condition for ?: got {:?}", v))),
+				}
+			},
 			Assign => {
-				let lv = self.eval_lvalue(&args[0])?;
 				let rv = self.eval_rvalue(&args[1])?;
-				self.assign(src, lv, rv)
-			}
-			_ => unimplemented!()
+				self.assign_expr(src, &args[0], rv)
+			},
+			AsAdd | AsSub | AsMul | AsDiv | AsMod | AsShl | AsShr | AsAnd | AsOr | AsXor => {
+				let curr = self.eval_rvalue(&args[0])?;
+				let rhs = self.eval_rvalue(&args[1])?;
+				// user defined compound assignment uses the corresponding binary operator
+				let token = ty.token();
+				let result = match self.eval_overloaded_op(src, &token[..token.len() - 1], &[curr.clone(), rhs.clone()])? {
+					Some(r) => r,
+					None => self.op_rvalues(src, basic_op.unwrap(), &[curr, rhs])?,
+				};
+				self.assign_expr(src, &args[0], result)
+			},
+			PreInc | PreDec | PostInc | PostDec => {
+				let curr = self.eval_rvalue(&args[0])?;
+				let result = self.op_rvalues(src, basic_op.unwrap(), &[curr.clone(), RValue::Constant(BitVector::from_u64(1, 1))])?;
+				self.assign_expr(src, &args[0], result)?;
+				if ty == PostInc || ty == PostDec {
+					Ok(curr)
+				} else {
+					// the value after conversion to the type of the target
+					self.eval_rvalue(&args[0])
+				}
+			},
+			_ => {
+				let mut values = Vec::new();
+				for a in args.iter() { values.push(self.eval_rvalue(a)?); }
+				match self.eval_overloaded_op(src, ty.token(), &values)? {
+					Some(r) => Ok(r),
+					None => self.op_rvalues(src, basic_op.unwrap(), &values),
+				}
+			},
 		}
 	}
 	pub fn eval_rvalue(&mut self, expr: &Expression) -> Result<RValue, CodegenError> {
+		self.with_attrs(expr.src, &expr.attrs, |e| e.eval_rvalue_ty(expr))
+	}
+	fn eval_rvalue_ty(&mut self, expr: &Expression) -> Result<RValue, CodegenError> {
 		use crate::ast::ExprType::*;
 		match &expr.ty { 
 			Literal(x) => Ok(RValue::Constant(x.clone())),
+			// strings are packed like an array of bytes; so the first character is the least significant
+			StrLiteral(s) => {
+				let mut bv = BitVector::new(s.len() * 8, false);
+				for (i, b) in s.bytes().enumerate() {
+					bv.set_slice(i * 8, &BitVector::from_u64(b as u64, 8));
+				}
+				Ok(RValue::Constant(bv))
+			},
 			Variable(v) => {
 				let var_idx = self.st.lookup_var(*v).unwrap_or_err(|| CodegenError(expr.src, format!("unable to resolve variable {}", v)))?;
+				self.note_read(expr.src, var_idx);
 				let value = self.st.vars.get(var_idx).value.clone();
 				if self.is_const && !value.is_fully_const() {
 					Err(CodegenError(expr.src, format!("attempting to use non-constant value {:?} in constant ctx", v)))
@@ -79,6 +362,49 @@ impl <'a> Eval<'a> {
 			Op(ty, args) => {
 				self.eval_oper(expr.src, *ty, args)
 			},
+			Cat(parts) => {
+				let mut values = Vec::new();
+				for part in parts.iter() {
+					values.push(self.eval_rvalue(part)?);
+				}
+				self.concat_values(expr.src, &values)
+			},
+			Rep(r) => {
+				let count = self.const_eval_scalar(&r.count)?.as_def_u64().unwrap_or(0) as usize;
+				if count == 0 {
+					return Err(CodegenError(expr.src, format!("replication count {} must be a positive constant", r.count)));
+				}
+				let value = self.eval_rvalue(&r.value)?;
+				self.concat_values(expr.src, &vec![value; count])
+			},
+			MemberAccess(base, m) => {
+				let base_value = self.eval_rvalue(base)?;
+				self.st.get_member(&base_value, *m, expr.src)
+			},
+			ArrAcc(a) => {
+				let mut value = self.eval_rvalue(&a.array)?;
+				for idx_expr in a.indices.iter() {
+					if matches!(value, RValue::Structure(_) | RValue::Union(_)) {
+						let idx = self.eval_rvalue(idx_expr)?;
+						let typ = value.to_type(&self.st);
+						value = self.eval_overloaded_op(expr.src, "[]", &[value, idx])?
+							.ok_or_else(|| CodegenError(expr.src, format!("no operator[] for type {:?}", typ)))?;
+						continue;
+					}
+					let idx = self.eval_index(idx_expr)?;
+					value = match value {
+						RValue::Array(mut vals) if idx < vals.len() => vals.swap_remove(idx),
+						v => return Err(CodegenError(expr.src, format!("index {} out of range for {:?}", idx, v))),
+					};
+				}
+				Ok(value)
+			},
+			List(items) => {
+				let mut values = Vec::new();
+				for i in items.iter() { values.push(self.eval_rvalue(i)?); }
+				Ok(RValue::Array(values))
+			},
+			Func(call) => self.eval_call(expr.src, call),
 			Null => Ok(RValue::Void),
 			_ => {unimplemented!()}
 		}
@@ -88,11 +414,53 @@ impl <'a> Eval<'a> {
 		match &expr.ty { 
 			Variable(v) => {
 				let var_idx = self.st.lookup_var(*v).unwrap_or_err(|| CodegenError(expr.src, format!("unable to resolve variable {}", v)))?;
+				let var = self.st.vars.get(var_idx);
+				if var.typ.is_const {
+					return Err(CodegenError(expr.src, format!("cannot assign to const variable {:?} declared at {:?}:{}:{}", v, var.src.file, var.src.start.line, var.src.start.col)));
+				}
 				Ok(LValue::from_var(var_idx))
 			},
+			MemberAccess(base, m) => {
+				let mut lv = self.eval_lvalue(base)?;
+				let decl = match self.lvalue_type(&lv).typ {
+					ResolvedTypes::Struct(key) => self.st.structs.get(&key).and_then(|s| s.member_decl(*m)),
+					_ => None,
+				};
+				lv.path.push(ValuePathItem::Member(*m));
+				if self.lvalue_type(&lv).is_const {
+					let var = self.st.vars.get(lv.var);
+					let decl = decl.unwrap_or(var.src);
+					return Err(CodegenError(expr.src, format!("cannot assign to const member {:?} of {:?} declared at {:?}:{}:{}", m, var.name, decl.file, decl.start.line, decl.start.col)));
+				}
+				Ok(lv)
+			},
+			ArrAcc(a) => {
+				let mut lv = self.eval_lvalue(&a.array)?;
+				for idx_expr in a.indices.iter() {
+					let idx = self.eval_index(idx_expr)?;
+					lv.path.push(ValuePathItem::ConstIndex(idx));
+				}
+				Ok(lv)
+			},
 			_ => {Err(CodegenError(expr.src, format!("{:?} is not a valid l-value", expr)))}
 		}
 	}
+	// The type of the part of a variable referred to by an l-value; const if any enclosing part is const
+	fn lvalue_type(&self, lv: &LValue) -> ResolvedType {
+		let mut typ = self.st.vars.get(lv.var).typ.clone();
+		for item in lv.path.iter() {
+			let inner = match (item, &typ.typ) {
+				(ValuePathItem::Member(m), ResolvedTypes::Struct(key)) => match self.st.structs.get(key).and_then(|s| s.member(*m)) {
+					Some(mt) => mt.clone(),
+					None => break,
+				},
+				(ValuePathItem::ConstIndex(_) | ValuePathItem::VarIndex(_), ResolvedTypes::Array(base, _)) => (**base).clone(),
+				_ => break,
+			};
+			typ = ResolvedType { is_const: typ.is_const | inner.is_const, ..inner };
+		}
+		typ
+	}
 	pub fn const_eval(&mut self, expr: &Expression) -> Result<RValue, CodegenError> {
 		let old_is_const = self.is_const;
 		self.is_const = true;
@@ -108,27 +476,181 @@ impl <'a> Eval<'a> {
 			Err(CodegenError(expr.src, format!("expected scalar constant got {:?}", result)))
 		}
 	}
+	// Declare a variable in the current scope
+	pub fn eval_var(&mut self, src: SrcInfo, v: &VariableDecl) -> Result<(), CodegenError> {
+		let var_init = if let Some(i) = &v.init {
+			Some(self.eval_rvalue(i)?)
+		} else {
+			None
+		};
+		let var_type = match (&v.ty.typ, &var_init) {
+			(DataTypes::Auto, Some(i)) => {
+				let mut typ = i.to_type(&self.st).unwrap();
+				typ.is_const = v.ty.is_const;
+				typ.is_static = v.ty.is_static;
+				typ
+			},
+			(DataTypes::Auto, None) => return Err(CodegenError(src, format!("auto variable {:?} requires an initialiser", v.name))),
+			_ => ResolvedType::do_resolve(self, &v.ty)?,
+		};
+		let name = self.st.decl_name(v.name);
+		let define = match self.st.scope().var_map.get(&name).copied() {
+			Some(idx) if self.defines.contains(&idx) => Some(self.st.vars.get(idx).value.clone()),
+			_ => None,
+		};
+		let var_value = match (&define, &var_init) {
+			(Some(d), _) => self.st.convert(d, &var_type, src)?,
+			(None, Some(i)) => self.st.convert(i, &var_type, src)?,
+			(None, None) => RValue::from_type(&self.st, &var_type.typ),
+		};
+		let var_idx = self.st.vars.add(Variable {name: v.name, typ: var_type, value: var_value, src: src, cond_idx: self.st.scope_cond_idx()});
+		self.st.scope().var_map.insert(name, var_idx);
+		Ok(())
+	}
+	// Resolve the value of an attribute; a bare name that isn't a variable is taken as a string
+	fn eval_attr(&mut self, src: SrcInfo, attr: &Attribute) -> Result<(IdString, Constant), CodegenError> {
+		let value = match &attr.value.ty {
+			ExprType::Null => Constant::Bits(BitVector::from_u64(1, 1)),
+			ExprType::StrLiteral(s) => Constant::Str(s.clone()),
+			ExprType::Variable(v) if self.st.lookup_var(*v).is_none() => Constant::Str(self.st.ids.get_str(*v).to_string()),
+			_ => Constant::Bits(self.const_eval_scalar(&attr.value)?),
+		};
+		attrs::check_attr(self.st.ids.get_str(attr.name), &value).map_err(|e| CodegenError(src, e))?;
+		Ok((attr.name, value))
+	}
+	// Run some elaboration with attributes given to every primitive it creates
+	fn with_attrs<T>(&mut self, src: SrcInfo, attrs: &AttributeList, f: impl FnOnce(&mut Self) -> Result<T, CodegenError>) -> Result<T, CodegenError> {
+		if attrs.0.is_empty() {
+			return f(self);
+		}
+		let count = self.st.attrs.len();
+		for attr in attrs.0.iter() {
+			let resolved = self.eval_attr(src, attr)?;
+			self.st.attrs.push(resolved);
+		}
+		let result = f(self);
+		self.st.attrs.truncate(count);
+		result
+	}
 	pub fn eval_st(&mut self, st: &Statement) -> Result<(), CodegenError> {
+		self.with_attrs(st.src, &st.attrs, |e| match &st.doc {
+			// documentation is passed on to the primitives created for the statement
+			Some(doc) => {
+				e.st.attrs.push((constids::doc, Constant::Str(doc.clone())));
+				let result = e.eval_st_ty(st);
+				e.st.attrs.pop();
+				result
+			},
+			None => e.eval_st_ty(st),
+		})
+	}
+	fn eval_st_ty(&mut self, st: &Statement) -> Result<(), CodegenError> {
 		use crate::ast::StatementType::*;
 		match &st.ty {
 			Null => {},
-			Var(v) => {
-				let var_init = if let Some(i) = &v.init {
-					self.eval_rvalue(i)?
+			Var(v) => self.eval_var(st.src, v)?,
+			VarList(vs) => {
+				for v in vs.iter() {
+					self.eval_var(st.src, v)?;
+				}
+			},
+			Typedef(td) => {
+				let typ = ResolvedType::do_resolve(self, &td.ty)?;
+				let name = self.st.decl_name(td.name);
+				self.st.scope().type_map.insert(name, typ);
+			},
+			Using(ud) => {
+				let typ = ResolvedType::do_resolve(self, &ud.ty)?;
+				let name = self.st.decl_name(ud.name);
+				self.st.scope().type_map.insert(name, typ);
+			},
+			Struct(sd) => {
+				let name = self.st.decl_name(sd.name);
+				if sd.specialisation.is_some() {
+					// specialisations are chosen between when the primary template is derived
+					let decl = self.st.scope().struct_map.get_mut(&name)
+						.ok_or_else(|| CodegenError(st.src, format!("specialisation of undeclared structure {:?}", sd.name)))?;
+					decl.specs.push(sd.clone());
 				} else {
-					RValue::Void
-				};
-				let var_type = ResolvedType::do_resolve(self, &v.ty)?;
-				let var_idx = self.st.vars.add(Variable {name: v.name, typ: var_type, value: var_init});
-				self.st.scope().var_map.insert(v.name, var_idx);
+					let ident = self.st.ident(name);
+					let ns_path = self.st.ns_path.clone();
+					self.st.scope().struct_map.insert(name, StructDecl { ident: ident, ns_path: ns_path, def: sd.clone(), specs: Vec::new() });
+				}
+			},
+			Func(f) => {
+				let name = self.st.decl_name(f.name);
+				let ns_path = self.st.ns_path.clone();
+				self.st.scope().func_map.entry(name).or_default().push(FuncDecl { ns_path: ns_path, func: f.clone(), templ: Vec::new() });
 			},
 			Block(b) => {
 				self.st.push_scope();
-				for b_st in b.iter() {
-					self.eval_st(b_st)?;
-				}
+				self.eval_sts(b)?;
 				self.st.pop_scope();
-			}
+			},
+			For(f) => {
+				// the scope is for variables declared in the init statement
+				self.st.push_scope();
+				self.eval_st(&f.init)?;
+				self.eval_loop(st, &f.cond, Some(&f.incr), &f.body, f.is_meta, true)?;
+				self.st.pop_scope();
+			},
+			RangeFor(f) => {
+				self.eval_range_for(st.src, f)?;
+			},
+			While(w) => {
+				self.eval_loop(st, &w.cond, None, &w.body, w.is_meta, !w.is_do_while)?;
+			},
+			Switch(sw) => {
+				self.eval_switch(st.src, &sw.value, &sw.body)?;
+			},
+			Case(_) | Default => {
+				return Err(CodegenError(st.src, format!("case label outside of a switch statement")));
+			},
+			Break | Continue | Return(_) => {
+				// continue skips over any enclosing switch statements to the innermost loop, return exits everything
+				// up to the function being called
+				let target = match &st.ty {
+					Break => self.loops.len().checked_sub(1).filter(|i| self.loops[*i].kind != LoopKind::Function),
+					Continue => self.loops.iter().rposition(|l| l.kind != LoopKind::Switch).filter(|i| self.loops[*i].kind == LoopKind::Loop),
+					_ => self.loops.iter().rposition(|l| l.kind == LoopKind::Function),
+				};
+				let target = target.ok_or_else(|| CodegenError(st.src, match &st.ty {
+					Break => format!("break outside of a loop or switch"),
+					Continue => format!("continue outside of a loop"),
+					_ => format!("return outside of a function"),
+				}))?;
+				if let Return(e) = &st.ty {
+					let value = self.eval_rvalue(e)?;
+					match (self.ret_var, &value) {
+						(None, RValue::Void) => {},
+						(None, _) => return Err(CodegenError(st.src, format!("returning a value from a void function"))),
+						(Some(_), RValue::Void) => return Err(CodegenError(st.src, format!("missing return value"))),
+						(Some(v), _) => self.st.assign_variable(v, &[], &value, st.src)?,
+					}
+				}
+				// the condition under which this break/continue/return is reached
+				let taken = self.cond_value(self.loops[target].cond_idx, st.src)?;
+				if self.loops[target].is_meta && !taken.is_fully_const() {
+					return Err(CodegenError(st.src, format!("break or continue under a runtime condition inside a meta loop")));
+				}
+				let not_taken = self.op_rvalues(st.src, BasicOp::LogNot, &[taken])?;
+				for i in target..self.loops.len() {
+					let (active, iter_active) = (self.loops[i].active.clone(), self.loops[i].iter_active.clone());
+					self.loops[i].iter_active = self.op_rvalues(st.src, BasicOp::LogAnd, &[iter_active, not_taken.clone()])?;
+					if st.ty != Continue || i > target {
+						self.loops[i].active = self.op_rvalues(st.src, BasicOp::LogAnd, &[active, not_taken.clone()])?;
+					}
+				}
+			},
+			StaticAssert(cond, msg) => {
+				if !self.const_eval_scalar(cond)?.as_bool() {
+					let mut err = format!("static assertion failed: {}", msg.clone().unwrap_or_else(|| format!("{}", cond)));
+					for inst in self.instantiations.iter().rev() {
+						err.push_str(&format!("\n  in instantiation of {}", inst));
+					}
+					return Err(CodegenError(st.src, err));
+				}
+			},
 			If(ifs) => {
 				let eval_cond = self.eval_rvalue(&ifs.cond)?;
 				if let RValue::Constant(c) = eval_cond {
@@ -143,14 +665,22 @@ impl <'a> Eval<'a> {
 						return Err(CodegenError(st.src, format!("expected constant condition for 'if constexpr' got {:?}", eval_cond)));
 					}
 					if let RValue::Node(n) = eval_cond {
+						let (start, vars_before, partial_before) = (self.branch_writes.len(), self.st.vars.count(), self.partial_vars.len());
+						let cond = format!("{}", ifs.cond);
 						self.st.push_cond(n, false);
+						self.branch_conds.push(cond.clone());
 						self.eval_st(&ifs.if_true)?;
+						self.branch_conds.pop();
 						self.st.pop_cond();
+						let mid = self.branch_writes.len();
 						if let Some(fls) = &ifs.if_false {
 							self.st.push_cond(n, true);
+							self.branch_conds.push(format!("!({})", cond));
 							self.eval_st(fls)?;
+							self.branch_conds.pop();
 							self.st.pop_cond();
 						}
+						self.merge_branch_writes(st.src, &cond, start, mid, vars_before, partial_before);
 					} else {
 						return Err(CodegenError(st.src, format!("expected scalar condition for 'if constexpr' got {:?}", eval_cond)));
 					}
@@ -163,26 +693,1843 @@ impl <'a> Eval<'a> {
 		}
 		Ok(())
 	}
+	// Evaluate a list of statements, stopping or predicating the remainder if a break or continue is reached
+	pub fn eval_sts(&mut self, sts: &[Statement]) -> Result<(), CodegenError> {
+		let mut pushed = 0;
+		let mut last_active = self.loops.last().map(|l| l.iter_active.clone());
+		for st in sts.iter() {
+			self.eval_st(st)?;
+			let active = self.loops.last().map(|l| l.iter_active.clone());
+			if active != last_active {
+				match &active {
+					Some(RValue::Constant(c)) if !c.as_bool() => break,
+					Some(RValue::Node(n)) => {
+						self.st.push_cond(*n, false);
+						pushed += 1;
+					},
+					_ => {},
+				}
+				last_active = active;
+			}
+		}
+		for _ in 0..pushed {
+			self.st.pop_cond();
+		}
+		Ok(())
+	}
+	// The conjunction of all conditions pushed since a given point in the condition stack
+	pub fn cond_value(&mut self, from: usize, src: SrcInfo) -> Result<RValue, CodegenError> {
+		let conds = Vec::from(&self.st.cond_stack()[from..]);
+		let mut result = RValue::Constant(BitVector::from_u64(1, 1));
+		for (node, inv) in conds.iter() {
+			let cond = self.op_rvalues(src, if *inv { BasicOp::LogNot } else { BasicOp::LogCast }, &[RValue::Node(*node)])?;
+			result = self.op_rvalues(src, BasicOp::LogAnd, &[result, cond])?;
+		}
+		Ok(result)
+	}
+	// The [[unroll=N]] bound given to a loop statement itself, if any; enclosing statements' attributes don't apply
+	fn unroll_bound(&mut self, st: &Statement) -> Result<Option<usize>, CodegenError> {
+		match st.attrs.0.iter().find(|a| a.name == constids::unroll) {
+			Some(attr) => match self.eval_attr(st.src, attr)?.1 {
+				Constant::Bits(b) => Ok(b.as_def_u64().map(|n| n as usize)),
+				_ => Ok(None),
+			},
+			None => Ok(None),
+		}
+	}
+	// Loops are fully unrolled. Each iteration is predicated on a single condition combining the loop condition
+	// (if it isn't constant) and any runtime breaks so far; unrolling ends when this becomes constant false.
+	// The increment is evaluated unpredicated, so that induction variables stay constant and unrolling can still end
+	// after a runtime break. The values it overwrites are recorded along with the condition that the loop exited since
+	// the previous increment, and restored under that condition once the loop is done.
+	pub fn eval_loop(&mut self, st: &Statement, cond: &Expression, incr: Option<&Expression>, body: &Statement, is_meta: bool, check_first: bool) -> Result<(), CodegenError> {
+		let src = st.src;
+		let unroll = self.unroll_bound(st)?;
+		let mut pushed = false;
+		let always = RValue::Constant(BitVector::from_u64(1, 1));
+		self.loops.push(LoopState { cond_idx: 0, active: always.clone(), iter_active: always.clone(), is_meta: is_meta, kind: LoopKind::Loop });
+		let (max_iters, max_runtime_iters) = match unroll {
+			Some(n) => (n, n),
+			None => (MAX_LOOP_ITERATIONS, MAX_RUNTIME_LOOP_ITERATIONS),
+		};
+		let mut iter = 0;
+		let mut runtime_iters = 0;
+		// whether the loop was active when the last increment was evaluated
+		let mut incr_active = always.clone();
+		let mut exits = Vec::new();
+		loop {
+			if iter > 0 || check_first {
+				let cond_value = match &cond.ty {
+					ExprType::Null => always.clone(),
+					_ => self.eval_rvalue(cond)?,
+				};
+				match &cond_value {
+					RValue::Constant(_) => {},
+					RValue::Node(_) if is_meta => return Err(CodegenError(src, format!("expected constant condition for meta loop got {:?}", cond_value))),
+					RValue::Node(_) => {},
+					v => return Err(CodegenError(src, format!("expected scalar loop condition got {:?}", v))),
+				}
+				let active = self.loops.last().unwrap().active.clone();
+				let active = self.op_rvalues(src, BasicOp::LogAnd, &[active, cond_value])?;
+				self.loops.last_mut().unwrap().active = active;
+			}
+			if !self.update_loop_cond(&mut pushed) {
+				break;
+			}
+			// fail before elaborating many copies of a body that depends on a runtime condition
+			if iter >= max_iters || (pushed && runtime_iters >= max_runtime_iters) {
+				return Err(CodegenError(src, match unroll {
+					Some(n) => format!("loop did not terminate within its unroll bound of {}", n),
+					None if pushed => format!("loop with a runtime condition did not terminate after {} iterations; bound it with [[unroll=N]]", max_runtime_iters),
+					None => format!("loop did not terminate after {} iterations", max_iters),
+				}));
+			}
+			if pushed {
+				runtime_iters += 1;
+			}
+			{
+				let cond_idx = self.st.cond_stack().len();
+				let l = self.loops.last_mut().unwrap();
+				l.cond_idx = cond_idx;
+				l.iter_active = l.active.clone();
+			}
+			self.eval_st(body)?;
+			if pushed {
+				self.st.pop_cond();
+				pushed = false;
+			}
+			let active = self.loops.last().unwrap().active.clone();
+			if let RValue::Constant(c) = &active {
+				if !c.as_bool() {
+					break;
+				}
+			}
+			if let Some(i) = incr {
+				if active == incr_active {
+					self.eval_rvalue(i)?;
+				} else {
+					let not_active = self.op_rvalues(src, BasicOp::LogNot, &[active.clone()])?;
+					let exited = self.op_rvalues(src, BasicOp::LogAnd, &[incr_active, not_active])?;
+					let before: Vec<(StoreIndex<Variable>, RValue)> = self.st.vars.iter().map(|(idx, v)| (idx, v.value.clone())).collect();
+					self.eval_rvalue(i)?;
+					let changed: Vec<_> = before.into_iter().filter(|(idx, value)| self.st.vars.get(*idx).value != *value).collect();
+					exits.push((exited, changed));
+					incr_active = active;
+				}
+			}
+			iter += 1;
+		}
+		if pushed {
+			self.st.pop_cond();
+		}
+		self.loops.pop();
+		// the exit conditions are mutually exclusive, so the order they are applied in doesn't matter
+		for (exited, changed) in exits.into_iter() {
+			let exited = self.st.get_node(&exited, src);
+			for (var, value) in changed.into_iter() {
+				let curr = self.st.vars.get(var).value.clone();
+				let restored = self.st.mux_value(exited, &curr, &value, src)?;
+				self.st.vars.get_mut(var).value = restored;
+			}
+		}
+		Ok(())
+	}
+	// The values iterated over by a range based for; either an array or a constant range(a, b)
+	fn eval_range(&mut self, src: SrcInfo, range: &Expression) -> Result<Vec<RValue>, CodegenError> {
+		if let ExprType::Func(call) = &range.ty {
+			let is_range = matches!(call.target.ty, ExprType::Variable(v) if v == constids::range && self.st.lookup_func(v).is_none());
+			if is_range && call.targs.is_empty() && (call.args.len() == 1 || call.args.len() == 2) {
+				let mut bounds = Vec::new();
+				for a in call.args.iter() {
+					bounds.push(self.const_eval_scalar(a)?);
+				}
+				let end = bounds.pop().unwrap();
+				let start = bounds.pop().unwrap_or_else(|| BitVector::from_u64(0, end.len()));
+				let (s, e) = match (start.as_def_u64(), end.as_def_u64()) {
+					(Some(s), Some(e)) => (s, e),
+					_ => return Err(CodegenError(src, format!("range bounds {:?} and {:?} must be defined", start, end))),
+				};
+				if e.saturating_sub(s) > MAX_LOOP_ITERATIONS as u64 {
+					return Err(CodegenError(src, format!("range of {} values exceeds the limit of {} iterations", e - s, MAX_LOOP_ITERATIONS)));
+				}
+				return Ok((s..e).map(|i| RValue::Constant(BitVector::from_u64(i, start.len()))).collect());
+			}
+		}
+		match self.eval_rvalue(range)? {
+			RValue::Array(vals) => Ok(vals),
+			v => Err(CodegenError(src, format!("unable to iterate over {:?}", v))),
+		}
+	}
+	// Range based for loops are unrolled like other loops, with the loop variable declared afresh for each element.
+	// When iterating by reference, the loop variable is written back to the array element after each iteration.
+	pub fn eval_range_for(&mut self, src: SrcInfo, f: &RangeForLoop) -> Result<(), CodegenError> {
+		let (by_ref, data_type) = match &f.var_type.typ {
+			DataTypes::Reference(inner) => (true, &**inner),
+			_ => (false, &f.var_type),
+		};
+		// the array is left alone when iterating by const reference
+		let target = if by_ref && !data_type.is_const {
+			Some(self.eval_lvalue(&f.range).map_err(|_| CodegenError(src, format!("iterating by reference requires an array variable, got {}", f.range)))?)
+		} else {
+			None
+		};
+		let values = self.eval_range(src, &f.range)?;
+		let always = RValue::Constant(BitVector::from_u64(1, 1));
+		self.loops.push(LoopState { cond_idx: 0, active: always.clone(), iter_active: always.clone(), is_meta: f.is_meta, kind: LoopKind::Loop });
+		let mut pushed = false;
+		for (i, value) in values.into_iter().enumerate() {
+			if !self.update_loop_cond(&mut pushed) {
+				break;
+			}
+			{
+				let cond_idx = self.st.cond_stack().len();
+				let l = self.loops.last_mut().unwrap();
+				l.cond_idx = cond_idx;
+				l.iter_active = l.active.clone();
+			}
+			self.st.push_scope();
+			// by reference, pick up any changes made to the array by earlier iterations
+			let value = match &target {
+				Some(_) => match self.eval_rvalue(&f.range)? {
+					RValue::Array(mut vals) => vals.swap_remove(i),
+					_ => value,
+				},
+				None => value,
+			};
+			let typ = match &data_type.typ {
+				DataTypes::Auto => {
+					let mut typ = value.to_type(&self.st).unwrap();
+					typ.is_const = data_type.is_const;
+					typ
+				},
+				_ => ResolvedType::do_resolve(self, data_type)?,
+			};
+			let value = self.st.convert(&value, &typ, src)?;
+			let var_idx = self.st.vars.add(Variable {name: f.var_name, typ: typ, value: value, src: src, cond_idx: self.st.scope_cond_idx()});
+			self.st.scope().var_map.insert(f.var_name, var_idx);
+			self.eval_st(&f.body)?;
+			if let Some(lv) = &target {
+				let mut path = lv.path.clone();
+				path.push(ValuePathItem::ConstIndex(i));
+				let new_value = self.st.vars.get(var_idx).value.clone();
+				self.st.assign_variable(lv.var, &path, &new_value, src)?;
+			}
+			self.st.pop_scope();
+			if pushed {
+				self.st.pop_cond();
+				pushed = false;
+			}
+			if let RValue::Constant(c) = &self.loops.last().unwrap().active {
+				if !c.as_bool() {
+					break;
+				}
+			}
+		}
+		if pushed {
+			self.st.pop_cond();
+		}
+		self.loops.pop();
+		Ok(())
+	}
+	// Replace the pushed loop condition with the current one, returns false if the loop is now never active
+	fn update_loop_cond(&mut self, pushed: &mut bool) -> bool {
+		if *pushed {
+			self.st.pop_cond();
+			*pushed = false;
+		}
+		match &self.loops.last().unwrap().active {
+			RValue::Constant(c) => c.as_bool(),
+			RValue::Node(n) => {
+				self.st.push_cond(*n, false);
+				*pushed = true;
+				true
+			},
+			_ => true,
+		}
+	}
+	// Each case is entered when its own predicate is true, or by falling through from the previous statement.
+	// As case values must be distinct constants, the predicates of cases that end in a break are mutually exclusive.
+	pub fn eval_switch(&mut self, src: SrcInfo, value: &Expression, body: &Statement) -> Result<(), CodegenError> {
+		let value = self.eval_rvalue(value)?;
+		let sts = match &body.ty {
+			StatementType::Block(b) => b,
+			_ => return Err(CodegenError(src, format!("expected block after switch"))),
+		};
+		let mut case_values: Vec<BitVector> = Vec::new();
+		let mut preds = Vec::new();
+		let mut default_idx = None;
+		let mut any_match = RValue::Constant(BitVector::from_u64(0, 1));
+		for (i, case) in sts.iter().enumerate() {
+			match &case.ty {
+				StatementType::Case(e) => {
+					let case_value = match self.eval_rvalue(e)? {
+						RValue::Constant(c) => c,
+						v => return Err(CodegenError(case.src, format!("expected constant case value got {:?}", v))),
+					};
+					if case_values.iter().any(|c| BasicOp::compare(c, &case_value) == Some(std::cmp::Ordering::Equal)) {
+						return Err(CodegenError(case.src, format!("duplicate case value {:?}", case_value)));
+					}
+					let pred = self.op_rvalues(case.src, BasicOp::Eq, &[value.clone(), RValue::Constant(case_value.clone())])?;
+					any_match = self.op_rvalues(case.src, BasicOp::LogOr, &[any_match, pred.clone()])?;
+					case_values.push(case_value);
+					preds.push(Some(pred));
+				},
+				StatementType::Default => {
+					if default_idx.is_some() {
+						return Err(CodegenError(case.src, format!("multiple default labels in one switch")));
+					}
+					default_idx = Some(i);
+					preds.push(None);
+				},
+				_ => preds.push(None),
+			}
+		}
+		if let Some(i) = default_idx {
+			preds[i] = Some(self.op_rvalues(src, BasicOp::LogNot, &[any_match])?);
+		}
+		let never = RValue::Constant(BitVector::from_u64(0, 1));
+		self.st.push_scope();
+		self.loops.push(LoopState { cond_idx: 0, active: never.clone(), iter_active: never.clone(), is_meta: false, kind: LoopKind::Switch });
+		let mut pushed = false;
+		for (st, pred) in sts.iter().zip(preds.into_iter()) {
+			match pred {
+				Some(p) => {
+					let active = self.loops.last().unwrap().active.clone();
+					let active = self.op_rvalues(st.src, BasicOp::LogOr, &[active, p])?;
+					let l = self.loops.last_mut().unwrap();
+					l.active = active.clone();
+					l.iter_active = active;
+				},
+				None => {
+					// statements that can never be reached are skipped
+					if !self.update_loop_cond(&mut pushed) {
+						continue;
+					}
+					let cond_idx = self.st.cond_stack().len();
+					self.loops.last_mut().unwrap().cond_idx = cond_idx;
+					self.eval_st(st)?;
+				}
+			}
+		}
+		if pushed {
+			self.st.pop_cond();
+		}
+		self.loops.pop();
+		self.st.pop_scope();
+		Ok(())
+	}
+	// Function calls are inlined. The body is evaluated in a new frame that only sees namespace level declarations
+	// and its parameters, with return predicating the rest of the body like break does in a loop.
+	pub fn eval_call(&mut self, src: SrcInfo, call: &FuncCall) -> Result<RValue, CodegenError> {
+		// assert(...) and assume(...) are intrinsics, unless shadowed by a function
+		if let ExprType::Variable(v) = call.target.ty {
+			if (v == constids::assert || v == constids::assume) && self.st.lookup_func(v).is_none() {
+				return self.eval_property(src, v, call);
+			}
+		}
+		let targs = self.resolve_templ_vals(&call.targs)?;
+		let (overloads, name, this) = match &call.target.ty {
+			ExprType::Variable(v) => {
+				let overloads = self.st.lookup_func(*v).cloned()
+					.ok_or_else(|| CodegenError(src, format!("unable to resolve function {:?}", v)))?;
+				(overloads, *v, None)
+			},
+			ExprType::MemberAccess(base, m) => {
+				let this = self.eval_rvalue(base)?;
+				let overloads = self.member_funcs(&this, *m)
+					.ok_or_else(|| CodegenError(src, format!("calling member function {:?} of non-structure value {:?}", m, this)))?;
+				if overloads.is_empty() {
+					return Err(CodegenError(src, format!("{:?} has no member function {:?}", this.to_type(&self.st).unwrap(), m)));
+				}
+				(overloads, *m, Some(this))
+			},
+			_ => return Err(CodegenError(src, format!("calling {} is not yet supported", call.target))),
+		};
+		let mut args = Vec::new();
+		for a in call.args.iter() {
+			args.push(self.eval_rvalue(a)?);
+		}
+		let decl = self.select_overload(src, name, overloads, &targs, &args)?;
+		let (value, new_this, ref_values) = self.call_function(src, &decl, this.as_ref(), &args)?;
+		// reference parameters, and the object of a member function, are written back in the context of the caller
+		for (i, v) in ref_values.into_iter() {
+			self.assign_expr(src, &call.args[i], v)?;
+		}
+		if let (ExprType::MemberAccess(base, _), Some(old_this), Some(new_this)) = (&call.target.ty, this, new_this) {
+			if new_this != old_this {
+				self.assign_expr(src, base, new_this)?;
+			}
+		}
+		Ok(value)
+	}
+	// A property that must hold whenever it is reached, becoming a primitive enabled by the current conditions
+	fn eval_property(&mut self, src: SrcInfo, kind: IdString, call: &FuncCall) -> Result<RValue, CodegenError> {
+		if call.args.len() != 1 || !call.targs.is_empty() {
+			return Err(CodegenError(src, format!("{:?} takes a single condition", kind)));
+		}
+		let value = self.eval_rvalue(&call.args[0])?;
+		if !value.is_scalar() {
+			return Err(CodegenError(src, format!("expected scalar condition for {:?} got {:?}", kind, value)));
+		}
+		let cond = self.op_rvalues(src, BasicOp::LogCast, &[value])?;
+		let enable = self.cond_value(0, src)?;
+		// properties that can never fail need no primitive
+		let is_const = |v: &RValue, b: bool| matches!(v, RValue::Constant(c) if c.is_defined() && c.as_bool() == b);
+		if is_const(&cond, true) || is_const(&enable, false) {
+			return Ok(RValue::Void);
+		}
+		let typ = if kind == constids::assert { PrimitiveType::Assert } else { PrimitiveType::Assume };
+		let prim_name = self.st.next_name(kind);
+		let prim = self.st.add_prim(prim_name, typ, src);
+		let cond_node = self.st.get_node(&cond, src);
+		let enable_node = self.st.get_node(&enable, src);
+		self.st.des.add_prim_input(prim, constids::A, cond_node).unwrap();
+		self.st.des.add_prim_input(prim, constids::EN, enable_node).unwrap();
+		Ok(RValue::Void)
+	}
+	// The member functions of a structure value with a given name, or None if the value isn't a structure
+	fn member_funcs(&self, value: &RValue, name: IdString) -> Option<Vec<FuncDecl>> {
+		match value.to_type(&self.st).map(|t| t.typ) {
+			Some(ResolvedTypes::Struct(key)) => Some(self.st.structs.get(&key).unwrap().functions.iter().filter(|f| f.func.name == name).cloned().collect()),
+			_ => None,
+		}
+	}
+	// Overloads are chosen by the number of arguments, taking defaults into account; then by whether their template
+	// arguments can be determined; and then by the types of any structure arguments. Of those remaining, plain functions
+	// are preferred over templates, and specialisations over the primary template; the more concrete the better.
+	fn select_overload(&mut self, src: SrcInfo, name: IdString, overloads: Vec<FuncDecl>, targs: &[ResolvedArg], args: &[RValue]) -> Result<FuncDecl, CodegenError> {
+		let overloads: Vec<_> = overloads.into_iter()
+			.filter(|d| args.len() <= d.func.func_args.len() && d.func.func_args[args.len()..].iter().all(|a| a.default.is_some()))
+			.collect();
+		if overloads.is_empty() {
+			return Err(CodegenError(src, format!("no overload of function {:?} takes {} arguments", name, args.len())));
+		}
+		let mut candidates = Vec::new();
+		for decl in overloads.iter() {
+			let f = &decl.func;
+			let (rank, bindings) = if let Some(pattern) = &f.specialisation {
+				// a specialisation is matched against the arguments of its primary template
+				let primary = overloads.iter().find(|d| d.func.specialisation.is_none() && !d.func.templ_args.is_empty() && d.func.func_args.len() == f.func_args.len())
+					.ok_or_else(|| CodegenError(f.src, format!("specialisation of {:?} has no matching primary template", name)))?;
+				let full = match self.deduce_templ_args(src, primary, targs, args)? {
+					Some(b) => b.into_iter().map(|(_, a)| a).collect::<Vec<_>>(),
+					None => continue,
+				};
+				match self.in_templ_frame(&decl.ns_path, &decl.templ, |e| e.match_spec(src, &f.templ_args, pattern, &full))? {
+					Some((bindings, concrete)) => ((1, concrete), bindings),
+					None => continue,
+				}
+			} else if !f.templ_args.is_empty() {
+				match self.deduce_templ_args(src, decl, targs, args)? {
+					Some(bindings) => ((0, 0), bindings),
+					None => continue,
+				}
+			} else if targs.is_empty() {
+				((2, 0), Vec::new())
+			} else {
+				continue;
+			};
+			let mut templ = decl.templ.clone();
+			templ.extend(bindings.into_iter());
+			candidates.push((rank, FuncDecl { ns_path: decl.ns_path.clone(), func: f.clone(), templ: templ }));
+		}
+		if candidates.len() > 1 {
+			let mut matching = Vec::new();
+			for (rank, decl) in candidates.into_iter() {
+				if self.in_templ_frame(&decl.ns_path, &decl.templ, |e| e.args_match(&decl.func, args))? {
+					matching.push((rank, decl));
+				}
+			}
+			candidates = matching;
+		}
+		let best = candidates.iter().map(|(r, _)| *r).max()
+			.ok_or_else(|| CodegenError(src, format!("no overload of function {:?} matches the arguments {:?}", name, args)))?;
+		let mut best_candidates: Vec<_> = candidates.into_iter().filter(|(r, _)| *r == best).collect();
+		if best_candidates.len() > 1 {
+			return Err(CodegenError(src, format!("ambiguous call to overloaded function {:?}", name)));
+		}
+		Ok(best_candidates.pop().unwrap().1)
+	}
+	// Structure arguments must be passed to parameters of the same type
+	fn args_match(&mut self, func: &Function, args: &[RValue]) -> Result<bool, CodegenError> {
+		for (arg, value) in func.func_args.iter().zip(args.iter()) {
+			let data_type = match &arg.data_type.typ {
+				DataTypes::Reference(inner) => &**inner,
+				_ => &arg.data_type,
+			};
+			let param_type = ResolvedType::do_resolve(self, data_type)?.typ;
+			let arg_type = value.to_type(&self.st).map(|t| t.typ);
+			let is_match = match (&param_type, &arg_type) {
+				(ResolvedTypes::Struct(p), Some(ResolvedTypes::Struct(a))) => p == a,
+				(ResolvedTypes::Struct(_), _) | (_, Some(ResolvedTypes::Struct(_))) => false,
+				_ => true,
+			};
+			if !is_match {
+				return Ok(false);
+			}
+		}
+		Ok(true)
+	}
+	// Resolve the template arguments given where a template is used
+	pub fn resolve_templ_vals(&mut self, vals: &[TemplateValue]) -> Result<Vec<ResolvedArg>, CodegenError> {
+		let mut result = Vec::new();
+		for v in vals.iter() {
+			result.push(match v {
+				TemplateValue::Typ(t) => ResolvedArg::Type(ResolvedType::do_resolve(self, t)?),
+				TemplateValue::Expr(e) => ResolvedArg::Const(self.const_eval_scalar(e)?),
+			});
+		}
+		Ok(result)
+	}
+	// Make template arguments visible in the current scope; types as types and values as constants
+	pub fn bind_templ(&mut self, bindings: &[(IdString, ResolvedArg)]) {
+		for (name, arg) in bindings.iter() {
+			match arg {
+				ResolvedArg::Type(t) => {
+					self.st.scope().type_map.insert(*name, t.clone());
+				},
+				ResolvedArg::Const(c) => {
+					let typ = ResolvedType { typ: ResolvedTypes::Integer(c.op_type()), is_const: true, is_static: false };
+					let var_idx = self.st.vars.add(Variable {name: *name, typ: typ, value: RValue::Constant(c.clone()), src: SrcInfo::default(), cond_idx: self.st.scope_cond_idx()});
+					self.st.scope().var_map.insert(*name, var_idx);
+				},
+			}
+		}
+	}
+	// Bind template parameters to their arguments in the current scope, in order so that defaults can refer to earlier
+	// parameters. Values are converted to the type of their parameter. None if an argument is missing without a default
+	pub fn bind_templ_args(&mut self, src: SrcInfo, params: &[TemplateArg], given: &[Option<ResolvedArg>]) -> Result<Option<Vec<(IdString, ResolvedArg)>>, CodegenError> {
+		if given.len() > params.len() {
+			return Err(CodegenError(src, format!("too many template arguments, expected at most {}", params.len())));
+		}
+		let mut bindings = Vec::new();
+		for (i, param) in params.iter().enumerate() {
+			let arg = match (&param.arg_type, given.get(i).cloned().flatten()) {
+				(TemplateArgType::Typename{..}, Some(ResolvedArg::Type(t))) => ResolvedArg::Type(t),
+				(TemplateArgType::Typename{default: Some(d)}, None) => ResolvedArg::Type(ResolvedType::do_resolve(self, d)?),
+				(TemplateArgType::Value{t, ..}, Some(ResolvedArg::Const(c))) => self.templ_value(src, param.name, t, c)?,
+				(TemplateArgType::Value{t, default: Some(d)}, None) => {
+					let c = self.const_eval_scalar(d)?;
+					self.templ_value(src, param.name, t, c)?
+				},
+				(_, None) => return Ok(None),
+				(TemplateArgType::Typename{..}, Some(a)) => return Err(CodegenError(src, format!("expected a type for template parameter {:?}, got {:?}", param.name, a))),
+				(TemplateArgType::Value{..}, Some(a)) => return Err(CodegenError(src, format!("expected a value for template parameter {:?}, got {:?}", param.name, a))),
+			};
+			let binding = (param.name, arg);
+			self.bind_templ(std::slice::from_ref(&binding));
+			bindings.push(binding);
+		}
+		Ok(Some(bindings))
+	}
+	fn templ_value(&mut self, src: SrcInfo, name: IdString, data_type: &DataType, value: BitVector) -> Result<ResolvedArg, CodegenError> {
+		let typ = ResolvedType::do_resolve(self, data_type)?;
+		match self.st.convert(&RValue::Constant(value), &typ, src)? {
+			RValue::Constant(c) => Ok(ResolvedArg::Const(c)),
+			_ => Err(CodegenError(src, format!("template parameter {:?} must have a scalar type", name))),
+		}
+	}
+	// Work out the template arguments of a function template for a call; explicit arguments come first, then typename
+	// parameters are deduced from the types of arguments passed to parameters of that type, then defaults are used.
+	// None if they can't all be determined.
+	fn deduce_templ_args(&mut self, src: SrcInfo, decl: &FuncDecl, targs: &[ResolvedArg], args: &[RValue]) -> Result<Option<Vec<(IdString, ResolvedArg)>>, CodegenError> {
+		let params = &decl.func.templ_args;
+		if targs.len() > params.len() {
+			return Ok(None);
+		}
+		let mut given: Vec<_> = (0..params.len()).map(|i| targs.get(i).cloned()).collect();
+		for (arg, value) in decl.func.func_args.iter().zip(args.iter()) {
+			let data_type = match &arg.data_type.typ {
+				DataTypes::Reference(inner) => &**inner,
+				_ => &arg.data_type,
+			};
+			let param = match &data_type.typ {
+				DataTypes::User(ut) if ut.args.is_empty() => params.iter().position(|p| p.name == ut.name && matches!(p.arg_type, TemplateArgType::Typename{..})),
+				_ => None,
+			};
+			if let (Some(i), Some(typ)) = (param.filter(|i| *i >= targs.len()), value.to_type(&self.st)) {
+				let typ = ResolvedArg::Type(ResolvedType { typ: typ.typ, is_const: false, is_static: false });
+				match &given[i] {
+					Some(prev) if *prev != typ => return Ok(None),
+					_ => given[i] = Some(typ),
+				}
+			}
+		}
+		self.in_templ_frame(&decl.ns_path, &decl.templ, |e| e.bind_templ_args(src, params, &given))
+	}
+	// Match the arguments of a template against the pattern of a specialisation, binding the parameters of the
+	// specialisation in the current scope. Returns the bindings and the number of concrete values in the pattern.
+	pub fn match_spec(&mut self, src: SrcInfo, params: &[TemplateArg], pattern: &[TemplateValue], args: &[ResolvedArg]) -> Result<Option<(Vec<(IdString, ResolvedArg)>, usize)>, CodegenError> {
+		if pattern.len() != args.len() {
+			return Ok(None);
+		}
+		let mut given: Vec<Option<ResolvedArg>> = vec![None; params.len()];
+		let mut concrete = Vec::new();
+		for (pat, arg) in pattern.iter().zip(args.iter()) {
+			let name = match pat {
+				TemplateValue::Typ(DataType { typ: DataTypes::User(ut), .. }) if ut.args.is_empty() => Some(ut.name),
+				TemplateValue::Expr(Expression { ty: ExprType::Variable(v), .. }) => Some(*v),
+				_ => None,
+			};
+			match name.and_then(|n| params.iter().position(|p| p.name == n)) {
+				Some(i) => {
+					let kind_match = match (&params[i].arg_type, arg) {
+						(TemplateArgType::Typename{..}, ResolvedArg::Type(_)) | (TemplateArgType::Value{..}, ResolvedArg::Const(_)) => true,
+						_ => false,
+					};
+					if !kind_match || given[i].as_ref().map(|g| g != arg).unwrap_or(false) {
+						return Ok(None);
+					}
+					given[i] = Some(arg.clone());
+				},
+				None => concrete.push((pat, arg)),
+			}
+		}
+		if given.iter().any(|g| g.is_none()) {
+			return Ok(None);
+		}
+		let bindings = self.bind_templ_args(src, params, &given)?.unwrap();
+		// concrete values may refer to parameters bound earlier in the pattern
+		for (pat, arg) in concrete.iter() {
+			let is_match = match (pat, arg) {
+				(TemplateValue::Typ(t), ResolvedArg::Type(a)) => ResolvedType::do_resolve(self, t)?.typ == a.typ,
+				(TemplateValue::Expr(e), ResolvedArg::Const(a)) => self.const_eval_scalar(e)?.resize(a.len(), a.is_signed) == *a,
+				_ => false,
+			};
+			if !is_match {
+				return Ok(None);
+			}
+		}
+		Ok(Some((bindings, concrete.len())))
+	}
+	// The most concrete of some specialisations that matches the given template arguments, and the bindings of its
+	// parameters
+	pub fn best_spec(&mut self, src: SrcInfo, specs: &[(&[TemplateArg], &[TemplateValue])], args: &[ResolvedArg]) -> Result<Option<(usize, Vec<(IdString, ResolvedArg)>)>, CodegenError> {
+		let ns_path = self.st.ns_path.clone();
+		let mut best: Option<(usize, usize, Vec<(IdString, ResolvedArg)>)> = None;
+		let mut ambiguous = false;
+		for (i, (params, pattern)) in specs.iter().enumerate() {
+			if let Some((bindings, concrete)) = self.in_templ_scope(&ns_path, &[], |e| e.match_spec(src, params, pattern, args))? {
+				match &best {
+					Some((_, c, _)) if *c > concrete => {},
+					Some((_, c, _)) if *c == concrete => ambiguous = true,
+					_ => {
+						best = Some((i, concrete, bindings));
+						ambiguous = false;
+					},
+				}
+			}
+		}
+		if ambiguous {
+			return Err(CodegenError(src, format!("ambiguous specialisation for template arguments {:?}", args)));
+		}
+		Ok(best.map(|(i, _, bindings)| (i, bindings)))
+	}
+	// Run some elaboration in a new scope for the given namespace, with template parameters bound
+	pub fn in_templ_scope<T>(&mut self, ns_path: &[IdString], templ: &[(IdString, ResolvedArg)], f: impl FnOnce(&mut Self) -> Result<T, CodegenError>) -> Result<T, CodegenError> {
+		let old_path = std::mem::replace(&mut self.st.ns_path, ns_path.to_vec());
+		self.st.push_scope();
+		self.bind_templ(templ);
+		let result = f(self);
+		self.st.pop_scope();
+		self.st.ns_path = old_path;
+		result
+	}
+	// Run some elaboration as part of the instantiation of a template with the given arguments
+	pub fn in_instantiation<T>(&mut self, name: IdString, templ: &[(IdString, ResolvedArg)], f: impl FnOnce(&mut Self) -> Result<T, CodegenError>) -> Result<T, CodegenError> {
+		if templ.is_empty() {
+			return f(self);
+		}
+		let args: Vec<_> = templ.iter().map(|(n, a)| format!("{:?} = {:?}", n, a)).collect();
+		self.instantiations.push(format!("{:?}<{}>", name, args.join(", ")));
+		let result = f(self);
+		self.instantiations.pop();
+		result
+	}
+	// As in_templ_scope, but in a new frame so that the local declarations of the caller aren't visible either
+	pub fn in_templ_frame<T>(&mut self, ns_path: &[IdString], templ: &[(IdString, ResolvedArg)], f: impl FnOnce(&mut Self) -> Result<T, CodegenError>) -> Result<T, CodegenError> {
+		let old_frame = self.st.frame;
+		self.st.frame = self.st.scope_depth();
+		let result = self.in_templ_scope(ns_path, templ, f);
+		self.st.frame = old_frame;
+		result
+	}
+	// Call a function with evaluated arguments, returning the result, the updated object for member functions, and the
+	// final value of any reference parameters
+	pub fn call_function(&mut self, src: SrcInfo, decl: &FuncDecl, this: Option<&RValue>, args: &[RValue]) -> Result<(RValue, Option<RValue>, Vec<(usize, RValue)>), CodegenError> {
+		if self.call_depth >= MAX_CALL_DEPTH {
+			return Err(CodegenError(src, format!("call to {:?} exceeds the maximum call depth of {}", decl.func.name, MAX_CALL_DEPTH)));
+		}
+		// with constant arguments the whole call is evaluated at elaboration time; the caller's runtime conditions are
+		// kept, as properties inside the call are only enabled under them
+		let is_const = this.into_iter().chain(args.iter()).all(|v| v.is_fully_const());
+		let old_is_const = self.is_const;
+		self.is_const |= is_const;
+		let old_loops = std::mem::take(&mut self.loops);
+		let old_ret_var = self.ret_var.take();
+		self.call_depth += 1;
+		let result = self.in_instantiation(decl.func.name, &decl.templ, |e| e.in_templ_frame(&decl.ns_path, &decl.templ, |e| e.eval_func_body(src, &decl.func, this, args)));
+		self.call_depth -= 1;
+		self.loops = old_loops;
+		self.ret_var = old_ret_var;
+		self.is_const = old_is_const;
+		result
+	}
+	// Call a user defined operator if any operand is a structure; either a member function of the first operand, or a
+	// free function
+	pub fn eval_overloaded_op(&mut self, src: SrcInfo, token: &str, values: &[RValue]) -> Result<Option<RValue>, CodegenError> {
+		if !values.iter().any(|v| matches!(v, RValue::Structure(_) | RValue::Union(_))) {
+			return Ok(None);
+		}
+		let name = self.st.ids.id(&format!("operator{}", token));
+		let members = self.member_funcs(&values[0], name).unwrap_or_default();
+		let (overloads, this, args) = if !members.is_empty() {
+			(members, Some(&values[0]), &values[1..])
+		} else if let Some(overloads) = self.st.lookup_func(name) {
+			(overloads.clone(), None, values)
+		} else {
+			return Err(CodegenError(src, format!("no operator{} defined for {:?}", token, values)));
+		};
+		let decl = self.select_overload(src, name, overloads, &[], args)?;
+		let (value, _, _) = self.call_function(src, &decl, this, args)?;
+		Ok(Some(value))
+	}
+	// Evaluate the body of a function in its own frame; returning the result and the final value of any reference parameters
+	fn eval_func_body(&mut self, src: SrcInfo, func: &Function, this: Option<&RValue>, args: &[RValue]) -> Result<(RValue, Option<RValue>, Vec<(usize, RValue)>), CodegenError> {
+		// members of the object are visible as variables in member functions
+		let mut member_vars = Vec::new();
+		if let Some(this) = this {
+			let typ = this.to_type(&self.st).unwrap();
+			let members = match &typ.typ {
+				ResolvedTypes::Struct(key) => self.st.structs.get(key).unwrap().members.clone(),
+				_ => unreachable!(),
+			};
+			for (name, mut mt) in members.into_iter() {
+				mt.is_const |= func.is_const;
+				let value = self.st.get_member(this, name, src)?;
+				let var_idx = self.st.vars.add(Variable {name: name, typ: mt, value: value.clone(), src: func.src, cond_idx: self.st.scope_cond_idx()});
+				self.st.scope().var_map.insert(name, var_idx);
+				member_vars.push((name, var_idx, value));
+			}
+		}
+		let mut ref_vars = Vec::new();
+		for (i, arg) in func.func_args.iter().enumerate() {
+			let (is_ref, data_type) = match &arg.data_type.typ {
+				DataTypes::Reference(inner) => (true, &**inner),
+				_ => (false, &arg.data_type),
+			};
+			let typ = ResolvedType::do_resolve(self, data_type)?;
+			let value = match args.get(i) {
+				Some(v) => v.clone(),
+				None => self.eval_rvalue(arg.default.as_ref().unwrap())?,
+			};
+			let value = self.st.convert(&value, &typ, src)?;
+			// nothing is written back through a const reference
+			let write_back = is_ref && !typ.is_const && i < args.len();
+			let var_idx = self.st.vars.add(Variable {name: arg.name, typ: typ, value: value, src: func.src, cond_idx: self.st.scope_cond_idx()});
+			self.st.scope().var_map.insert(arg.name, var_idx);
+			if write_back {
+				ref_vars.push((i, var_idx));
+			}
+		}
+		let ret_type = ResolvedType::do_resolve(self, &func.ret_type)?;
+		self.ret_var = match ret_type.typ {
+			ResolvedTypes::Void => None,
+			_ => {
+				let value = RValue::from_type(&self.st, &ret_type.typ);
+				Some(self.st.vars.add(Variable {name: func.name, typ: ret_type, value: value, src: func.src, cond_idx: self.st.scope_cond_idx()}))
+			},
+		};
+		let always = RValue::Constant(BitVector::from_u64(1, 1));
+		let cond_idx = self.st.cond_stack().len();
+		self.loops.push(LoopState { cond_idx: cond_idx, active: always.clone(), iter_active: always, is_meta: false, kind: LoopKind::Function });
+		self.eval_st(&func.content)?;
+		self.loops.pop();
+		let value = match self.ret_var {
+			Some(v) => self.st.vars.get(v).value.clone(),
+			None => RValue::Void,
+		};
+		let ref_values = ref_vars.iter().map(|(i, v)| (*i, self.st.vars.get(*v).value.clone())).collect();
+		// only members that were changed are written back, as union members overlap
+		let new_this = match this {
+			Some(this) => {
+				let typ = this.to_type(&self.st).unwrap();
+				let mut new_this = this.clone();
+				for (name, var_idx, old_value) in member_vars.into_iter() {
+					let value = self.st.vars.get(var_idx).value.clone();
+					if value != old_value {
+						new_this = self.st.assign_path(name, new_this, &typ, &[ValuePathItem::Member(name)], &value, None, src)?;
+					}
+				}
+				Some(new_this)
+			},
+			None => None,
+		};
+		Ok((value, new_this, ref_values))
+	}
+	// Declare everything at namespace level, so it is visible to blocks
+	pub fn eval_namespace(&mut self, ns: &Namespace) -> Result<(), CodegenError> {
+		for item in ns.content.iter() {
+			match item {
+				NamespaceItem::Namespace(inner) => {
+					self.st.ns_path.push(inner.name.unwrap());
+					self.eval_namespace(inner)?;
+					self.st.ns_path.pop();
+				},
+				NamespaceItem::Stmt(st) => match &st.ty {
+					StatementType::Var(_) | StatementType::VarList(_) | StatementType::Typedef(_) | StatementType::Using(_) | StatementType::Struct(_) | StatementType::Func(_)
+						| StatementType::StaticAssert(..) => self.eval_st(st)?,
+					StatementType::Module(_) | StatementType::Null => {},
+					_ => return Err(CodegenError(st.src, format!("unexpected statement at namespace level"))),
+				},
+			}
+		}
+		Ok(())
+	}
 	pub fn eval_mod(&mut self, m: &crate::ast::Module) -> Result<(), CodegenError> {
+		// ports are in their own scope, so they shadow namespace level declarations
+		self.st.push_scope();
+		let mut outputs = Vec::new();
 		for port in m.ports.iter() {
 			match &port.dir {
 				IODir::Input => {
 					let ty = ResolvedType::do_resolve(self, &port.arg_type)?;
-					let n = self.st.des.add_port(port.name, ty.pack()?, PortDir::Input).unwrap();
-					let var_idx = self.st.vars.add(Variable {name: port.name, typ: ty, value: RValue::Node(n)});
+					let n = self.st.des.add_port(port.name, ty.pack(&self.st)?, PortDir::Input, m.src).unwrap();
+					let value = self.st.unpack_value(&RValue::Node(n), &ty, m.src)?;
+					let var_idx = self.st.vars.add(Variable {name: port.name, typ: ty, value: value, src: m.src, cond_idx: self.st.scope_cond_idx()});
+					self.st.scope().var_map.insert(port.name, var_idx);
+				}
+				IODir::Output => {
+					let ty = ResolvedType::do_resolve(self, &port.arg_type)?;
+					let value = RValue::from_type(&self.st, &ty.typ);
+					let var_idx = self.st.vars.add(Variable {name: port.name, typ: ty, value: value, src: m.src, cond_idx: self.st.scope_cond_idx()});
 					self.st.scope().var_map.insert(port.name, var_idx);
+					outputs.push(var_idx);
 				}
-				_ => {},
 			}
 		};
 		self.eval_st(&m.content)?;
+		// outputs are driven by their final values, which should be fully defined
+		for var_idx in outputs.into_iter() {
+			let (name, typ, value) = {
+				let v = self.st.vars.get(var_idx);
+				(v.name, v.typ.clone(), v.value.clone())
+			};
+			if !self.note_read(m.src, var_idx) && value.has_undef() {
+				self.warnings.push(CodegenWarning(m.src, format!("output {:?} is not fully assigned", name)));
+			}
+			let packed = self.st.pack_value(&value, &typ, m.src)?;
+			let node = self.st.get_node(&packed, m.src);
+			self.st.des.add_output(name, node, m.src).map_err(|e| CodegenError(m.src, e))?;
+		}
+		self.check_register_inputs();
+		self.st.pop_scope();
 		Ok(())
 	}
-	pub fn init(ids: &'a mut IdStringDb, m: &crate::ast::Module) -> Self {
-		let state = GenState::new(ids, m.name);
+	// Warn about registers that may be loaded with undefined bits; from an undefined constant anywhere in the logic
+	// feeding them in the same cycle
+	pub fn check_register_inputs(&mut self) {
+		let des = &self.st.des;
+		// whether each node may be undefined, once known
+		let mut undef: Vec<Option<bool>> = vec![None; des.nodes.size()];
+		fn may_be_undef(des: &Design, node: StoreIndex<Node>, undef: &mut Vec<Option<bool>>) -> bool {
+			if let Some(known) = undef[node.index()] {
+				return known;
+			}
+			// assume the best while visiting, to stop at loops
+			undef[node.index()] = Some(false);
+			let prim = des.prims.get(des.nodes.get(node).driver.prim);
+			let result = match &prim.typ {
+				PrimitiveType::Constant(c) => !c.is_defined(),
+				PrimitiveType::TopPort | PrimitiveType::Reg(_) | PrimitiveType::Mem(_) => false,
+				_ => prim.ports.iter().filter(|(_, p)| !p.is_output()).any(|(_, p)| may_be_undef(des, p.node.unwrap(), undef)),
+			};
+			undef[node.index()] = Some(result);
+			result
+		}
+		let mut found = Vec::new();
+		for (_, prim) in des.prims.iter().filter(|(_, p)| matches!(p.typ, PrimitiveType::Reg(_))) {
+			for (_, port) in prim.ports.iter().filter(|(_, p)| !p.is_output()) {
+				if may_be_undef(des, port.node.unwrap(), &mut undef) {
+					found.push((prim.src, prim.name, port.name));
+				}
+			}
+		}
+		for (src, reg, port) in found.into_iter() {
+			self.warnings.push(CodegenWarning(src, format!("register {} may be loaded with an undefined value through {}",
+				self.st.ids.get_str(reg), self.st.ids.get_str(port))));
+		}
+	}
+	// Elaborate a block with the defaults of any template parameters, using the most concrete matching specialisation of
+	// it if there is one. Returns false if some template parameters have no default, so the block can't be elaborated
+	// on its own
+	pub fn eval_block(&mut self, m: &crate::ast::Module, specs: &[&crate::ast::Module]) -> Result<bool, CodegenError> {
+		let ns_path = self.st.ns_path.clone();
+		let bindings = match self.in_templ_scope(&ns_path, &[], |e| e.bind_templ_args(m.src, &m.templ_args, &[]))? {
+			Some(bindings) => bindings,
+			None => return Ok(false),
+		};
+		let args: Vec<_> = bindings.iter().map(|(_, a)| a.clone()).collect();
+		let patterns: Vec<_> = specs.iter().map(|s| (&s.templ_args[..], &s.specialisation.as_ref().unwrap()[..])).collect();
+		let (block, bindings) = match self.best_spec(m.src, &patterns, &args)? {
+			Some((i, spec_bindings)) => (specs[i], spec_bindings),
+			None => (m, bindings),
+		};
+		self.in_instantiation(m.name, &bindings, |e| e.in_templ_scope(&ns_path, &bindings, |e| e.eval_mod(block)))?;
+		Ok(true)
+	}
+	// The specialisations of a block declared in the same namespace
+	pub fn block_specs<'b>(ns: &'b Namespace, m: &crate::ast::Module) -> Vec<&'b crate::ast::Module> {
+		ns.content.iter().filter_map(|item| match item {
+			NamespaceItem::Stmt(Statement { ty: StatementType::Module(s), .. }) if s.name == m.name && s.specialisation.is_some() => Some(s),
+			_ => None,
+		}).collect()
+	}
+	// Elaborate the namespace level declarations of a design once, so they can be shared by all of its blocks
+	pub fn eval_globals(ids: &mut IdStringDb, root: &Namespace) -> Result<Globals, CodegenError> {
+		Eval::eval_globals_with_defines(ids, root, &[])
+	}
+	// Defines (e.g. from the command line) are global constants, declared before anything in the design. A global of the
+	// same name takes the value of the define instead of its initialiser, so it acts as a default
+	pub fn eval_globals_with_defines(ids: &mut IdStringDb, root: &Namespace, defines: &[(IdString, BitVector)]) -> Result<Globals, CodegenError> {
+		let name = ids.id("$global");
+		let mut e = Eval::init(ids, name, &Globals::new());
+		for (name, value) in defines.iter() {
+			let value = RValue::Constant(value.clone());
+			let mut typ = value.to_type(&e.st).unwrap();
+			typ.is_const = true;
+			let var_idx = e.st.vars.add(Variable {name: *name, typ: typ, value: value, src: SrcInfo::default(), cond_idx: e.st.scope_cond_idx()});
+			let decl_name = e.st.decl_name(*name);
+			e.st.scope().var_map.insert(decl_name, var_idx);
+			e.defines.push(var_idx);
+		}
+		e.eval_namespace(root)?;
+		Ok(e.st.into_globals())
+	}
+	pub fn init(ids: &'a mut IdStringDb, name: IdString, globals: &Globals) -> Self {
+		let state = GenState::new(ids, name, globals);
 		Self {
 			st: state,
 			is_const: false,
+			loops: Vec::new(),
+			ret_var: None,
+			call_depth: 0,
+			instantiations: Vec::new(),
+			branch_conds: Vec::new(),
+			branch_writes: Vec::new(),
+			partial_vars: Vec::new(),
+			warnings: Vec::new(),
+			defines: Vec::new(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::parser::{Tokeniser, ParserState, Parser};
+	use crate::ast::StatementType;
+	use crate::design::SpecialOperation;
+
+	// The first block in a namespace or its children, and the namespace it is in
+	fn find_block<'a>(ns: &'a Namespace, path: &mut Vec<IdString>) -> Option<(&'a Namespace, &'a crate::ast::Module)> {
+		for item in ns.content.iter() {
+			match item {
+				NamespaceItem::Stmt(st) => if let StatementType::Module(m) = &st.ty { return Some((ns, m)) },
+				NamespaceItem::Namespace(inner) => {
+					path.push(inner.name.unwrap());
+					if let Some(m) = find_block(inner, path) {
+						return Some(m);
+					}
+					path.pop();
+				},
+			}
+		}
+		None
+	}
+	// Elaborate the first block in some source code, then run some checks on the result
+	fn elaborate<F: FnOnce(&mut Eval)>(src: &str, check: F) -> Result<(), CodegenError> {
+		elaborate_with_defines(src, &[], check)
+	}
+	fn elaborate_with_defines<F: FnOnce(&mut Eval)>(src: &str, defines: &[(&str, u64)], check: F) -> Result<(), CodegenError> {
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let tok = Tokeniser::new(ids.id("<test>"), src.chars());
+		let ps = ParserState::new(tok, &mut ids).unwrap();
+		let mut p = Parser::new(ps);
+		let root = p.do_parse(&mut ids).unwrap();
+		let mut path = Vec::new();
+		let (ns, m) = find_block(&root, &mut path).unwrap();
+		let defines: Vec<_> = defines.iter().map(|(n, v)| (ids.id(n), BitVector::from_u64(*v, 64))).collect();
+		let globals = Eval::eval_globals_with_defines(&mut ids, &root, &defines)?;
+		let mut e = Eval::init(&mut ids, m.name, &globals);
+		e.st.ns_path = path;
+		assert!(e.eval_block(m, &Eval::block_specs(ns, m))?);
+		check(&mut e);
+		Ok(())
+	}
+	// Final value of the last variable declared with a given name
+	fn var_value(e: &mut Eval, name: &str) -> RValue {
+		let id = e.st.ids.id(name);
+		e.st.vars.iter().filter(|(_, v)| v.name == id).last().unwrap().1.value.clone()
+	}
+
+	// Evaluate a value in the elaborated design, given values for the input ports
+	fn sim(e: &mut Eval, value: &RValue, inputs: &[(&str, u64)]) -> BitVector {
+		use crate::design::{Design, Node};
+		use crate::core::{State, StoreIndex};
+		let inputs: Vec<(IdString, u64)> = inputs.iter().map(|(k, v)| (e.st.ids.id(k), *v)).collect();
+		let sel_ports: Vec<IdString> = (0..64).map(|i| e.st.ids.id(&format!("S{}", i))).collect();
+		fn sim_node(des: &Design, node: StoreIndex<Node>, inputs: &[(IdString, u64)], sel_ports: &[IdString]) -> BitVector {
+			use crate::design::SpecialOperation::*;
+			let n = des.nodes.get(node);
+			let prim = des.prims.get(n.driver.prim);
+			let input = |name: IdString| sim_node(des, prim.ports.named(name).unwrap().node.unwrap(), inputs, sel_ports);
+			match &prim.typ {
+				PrimitiveType::TopPort => BitVector::from_u64(inputs.iter().find(|(k, _)| *k == n.name).unwrap().1, n.typ.width),
+				PrimitiveType::Constant(c) => c.clone(),
+				PrimitiveType::BasicOp(op) => {
+					let args: Vec<BitVector> = [constids::A, constids::B].iter().filter(|p| prim.ports.named(**p).is_some()).map(|p| input(*p)).collect();
+					op.apply(&args).resize(n.typ.width, n.typ.is_signed)
+				},
+				PrimitiveType::SpecOp(SliceGetFix { offset, width }) => input(constids::A).slice(*offset, *width),
+				PrimitiveType::SpecOp(SliceSetFix { offset, .. }) => {
+					let mut result = input(constids::A);
+					result.set_slice(*offset, &input(constids::B));
+					result
+				},
+				PrimitiveType::SpecOp(Resize(width)) => input(constids::A).resize(*width, n.typ.is_signed),
+				PrimitiveType::SpecOp(Cat) => {
+					let (lo, hi) = (input(constids::A), input(constids::B));
+					let mut result = lo.resize(n.typ.width, false);
+					result.set_slice(lo.len(), &hi);
+					result
+				},
+				PrimitiveType::Cond { inv } => {
+					let taken = (0..inv.len()).all(|i| input(sel_ports[i]).as_bool() != (inv.get(i) == Some(State::S1)));
+					input(if taken { constids::B } else { constids::A })
+				},
+				t => panic!("unsupported primitive {:?} in simulation", t),
+			}
+		}
+		match value {
+			RValue::Constant(c) => c.clone(),
+			RValue::Node(n) => sim_node(&e.st.des, *n, &inputs, &sel_ports),
+			v => panic!("unable to simulate {:?}", v),
+		}
+	}
+
+	#[test]
+	fn union_const() -> Result<(), CodegenError> {
+		elaborate("block foo() -> () {
+			struct halves { unsigned<4> lo; unsigned<4> hi; };
+			union insn { unsigned<8> raw; halves h; };
+			insn i = {0xA5};
+			unsigned<4> hi = i.h.hi;
+			i.h.lo = 3;
+			unsigned<8> raw = i.raw;
+		}", |e| {
+			assert_eq!(var_value(e, "hi"), RValue::Constant(BitVector::from_u64(0xA, 4)));
+			assert_eq!(var_value(e, "raw"), RValue::Constant(BitVector::from_u64(0xA3, 8)));
+		})
+	}
+
+	#[test]
+	fn union_runtime() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<8> a) -> () {
+			struct halves { unsigned<4> lo; unsigned<4> hi; };
+			union insn { unsigned<8> raw; halves h; };
+			insn i;
+			i.raw = a;
+			unsigned<4> hi = i.h.hi;
+		}", |e| {
+			match var_value(e, "hi") {
+				RValue::Node(n) => {
+					let drv = e.st.des.nodes.get(n).driver.prim;
+					assert_eq!(e.st.des.prims.get(drv).typ, PrimitiveType::SpecOp(SpecialOperation::SliceGetFix { offset: 4, width: 4 }));
+				},
+				v => panic!("expected node, got {:?}", v),
+			}
+		})
+	}
+
+	#[test]
+	fn const_loops() -> Result<(), CodegenError> {
+		elaborate("block foo() -> () {
+			unsigned<16> sum = 0;
+			for meta (int i = 0; i < 10; i++) sum += i;
+			unsigned<16> w = 0;
+			int j = 0;
+			while (j < 5) { w = w + 2; j++; }
+			int k = 0;
+			do { k++; } while (k < 0);
+			int m = 0;
+			while (1) { m++; if (m == 7) break; }
+			int odd = 0;
+			for (int i = 0; i < 10; i++) { if ((i & 1) == 0) continue; odd += i; }
+		}", |e| {
+			assert_eq!(var_value(e, "sum"), RValue::Constant(BitVector::from_u64(45, 16)));
+			assert_eq!(var_value(e, "w"), RValue::Constant(BitVector::from_u64(10, 16)));
+			assert_eq!(var_value(e, "k"), RValue::Constant(BitVector::from_u64(1, 32).resize(32, true)));
+			assert_eq!(var_value(e, "m"), RValue::Constant(BitVector::from_u64(7, 32).resize(32, true)));
+			assert_eq!(var_value(e, "odd"), RValue::Constant(BitVector::from_u64(25, 32).resize(32, true)));
+		})
+	}
+
+	#[test]
+	fn runtime_loops() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<8> a) -> () {
+			unsigned<8> x = 0;
+			for (int i = 0; i < 4; i++) { if (a == i) break; x++; }
+			unsigned<8> n = 0;
+			for (int c = 0; c < 6; c++) { if (n >= a) break; n++; }
+			unsigned<8> s = 0;
+			unsigned<8> t = 0;
+			do { s += 3; if (s > a) continue; t++; } while (s < 12);
+		}", |e| {
+			for a in 0..8 {
+				let x = var_value(e, "x");
+				assert_eq!(sim(e, &x, &[("a", a)]).as_u64(), std::cmp::min(a, 4));
+				let n = var_value(e, "n");
+				assert_eq!(sim(e, &n, &[("a", a)]).as_u64(), std::cmp::min(a, 6));
+				let t = var_value(e, "t");
+				assert_eq!(sim(e, &t, &[("a", a)]).as_u64(), (1..=4).filter(|i| i * 3 <= a).count() as u64);
+			}
+		})?;
+		// induction variables keep the value they had when the loop was exited at runtime
+		elaborate("block foo(unsigned<8> a) -> () {
+			unsigned<8> i = 0;
+			for (i = 0; i < 4; i++) { if (a == i) break; }
+			unsigned<8> r = i;
+			unsigned<8> j = 0;
+			for (j = 0; j < 6 && j < a; j++) {}
+			unsigned<8> q = j;
+		}", |e| {
+			for a in 0..8 {
+				let r = var_value(e, "r");
+				assert_eq!(sim(e, &r, &[("a", a)]).as_u64(), std::cmp::min(a, 4));
+				let q = var_value(e, "q");
+				assert_eq!(sim(e, &q, &[("a", a)]).as_u64(), std::cmp::min(a, 6));
+			}
+		})?;
+		let err = elaborate("block foo(unsigned<8> a) -> () { unsigned<8> n = 0; while (n < a) n++; }", |_| {});
+		assert!(err.unwrap_err().1.contains("did not terminate after 256 iterations"));
+		let err = elaborate("block foo(unsigned<8> a) -> () { unsigned<8> n = 0; [[unroll=8]] while (n < a) n++; }", |_| {});
+		assert!(err.unwrap_err().1.contains("unroll bound of 8"));
+		elaborate("block foo(unsigned<8> a) -> () { unsigned<8> n = 0; [[unroll=4]] for (int i = 0; i < 4; i++) { if (n >= a) break; n++; } }", |_| {})?;
+		let err = elaborate("block foo(unsigned<8> a) -> () { unsigned<8> n = 0; while meta (n < a) n++; }", |_| {});
+		assert!(err.unwrap_err().1.contains("meta loop"));
+		Ok(())
+	}
+
+	#[test]
+	fn branch_local_conditions() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<8> a) -> () {
+			unsigned<8> x = 0;
+			if (a > 3) {
+				unsigned<8> t = a;
+				t = t + 1;
+				if (a > 5) t = t + 2;
+				x = t;
+			}
+		}", |e| {
+			// only the assignment in the inner if and the one to x need Cond primitives, each with a single condition
+			let conds: Vec<_> = e.st.des.prims.iter().filter_map(|(_, p)| match &p.typ { PrimitiveType::Cond { inv } => Some(inv.len()), _ => None }).collect();
+			assert_eq!(conds, vec![1, 1]);
+			for a in 0..8 {
+				let x = var_value(e, "x");
+				assert_eq!(sim(e, &x, &[("a", a)]).as_u64(), match a { 0..=3 => 0, 4 | 5 => a + 1, _ => a + 3 });
+			}
+		})
+	}
+
+	#[test]
+	fn switch_case() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<4> op) -> () {
+			unsigned<8> x = 0;
+			switch (op) {
+				case 0: x = 10; break;
+				case 1:
+				case 2: x = 20; break;
+				case 3: x = 30;
+				case 4: x += 5; break;
+				default: x = 99;
+			}
+			unsigned<8> y = 0;
+			switch (2) {
+				case 1: y = 1; break;
+				case 2: y = 2;
+				default: y += 1;
+			}
+			unsigned<8> z = 0;
+			for (int i = 0; i < 4; i++) {
+				switch (i) {
+					case 1: continue;
+					case 3: if (op == 5) break; z += 100; break;
+				}
+				z++;
+			}
+		}", |e| {
+			assert_eq!(var_value(e, "y"), RValue::Constant(BitVector::from_u64(3, 8)));
+			for op in 0..16 {
+				let expected = match op { 0 => 10, 1 | 2 => 20, 3 => 35, 4 => 5, _ => 99 };
+				let x = var_value(e, "x");
+				assert_eq!(sim(e, &x, &[("op", op)]).as_u64(), expected);
+				let z = var_value(e, "z");
+				assert_eq!(sim(e, &z, &[("op", op)]).as_u64(), if op == 5 { 3 } else { 103 });
+			}
+		})?;
+		let err = elaborate("block foo(unsigned<4> op) -> () { switch (op) { case 1: case 1: break; } }", |_| {});
+		assert!(err.unwrap_err().1.contains("duplicate case value"));
+		let err = elaborate("block foo(unsigned<4> op) -> () { switch (op) { case 1: continue; } }", |_| {});
+		assert!(err.unwrap_err().1.contains("continue outside of a loop"));
+		Ok(())
+	}
+
+	#[test]
+	fn ternary() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<4> a) -> () {
+			struct pair { unsigned<4> lo; unsigned<4> hi; };
+			unsigned<8> k = 1 ? 3 : 4;
+			unsigned<8> m = a > 5 ? a : a < 2 ? 0 : 15;
+			unsigned<8> n = 0;
+			unsigned<8> t = (a == 2) ? n++ : n--;
+			pair p = {1, 2};
+			pair q = {3, 4};
+			pair r = (a & 1) != 0 ? p : q;
+		}", |e| {
+			assert_eq!(var_value(e, "k"), RValue::Constant(BitVector::from_u64(3, 8)));
+			for a in 0..16 {
+				let m = var_value(e, "m");
+				assert_eq!(sim(e, &m, &[("a", a)]).as_u64(), if a > 5 { a } else if a < 2 { 0 } else { 15 });
+				let n = var_value(e, "n");
+				assert_eq!(sim(e, &n, &[("a", a)]).as_u64(), if a == 2 { 1 } else { 255 });
+				let r = var_value(e, "r");
+				let r = match &r { RValue::Structure(sv) => sv.values[&e.st.ids.id("lo")].clone(), _ => panic!() };
+				assert_eq!(sim(e, &r, &[("a", a)]).as_u64(), if a & 1 != 0 { 1 } else { 3 });
+			}
+		})
+	}
+
+	#[test]
+	fn cat_rep() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<4> a, unsigned<8> b) -> () {
+			unsigned<4> x = 0xA;
+			unsigned<2> y = 1;
+			auto k = cat(x, y, rep<3>(y));
+			auto c = cat(a, x);
+			unsigned<4> hi = 0;
+			unsigned<4> lo = 0;
+			cat(hi, lo) = b;
+			unsigned<2> p = 0;
+			unsigned<2> q = 0;
+			cat(p, q) += 1;
+		}", |e| {
+			assert_eq!(var_value(e, "k"), RValue::Constant(BitVector::from_u64(0b1010_01_010101, 12)));
+			for a in 0..16 {
+				let c = var_value(e, "c");
+				assert_eq!(sim(e, &c, &[("a", a)]).as_u64(), (a << 4) | 0xA);
+			}
+			for b in [0x00, 0x5A, 0xFF, 0x81].iter() {
+				let hi = var_value(e, "hi");
+				let lo = var_value(e, "lo");
+				assert_eq!(sim(e, &hi, &[("b", *b)]).as_u64(), b >> 4);
+				assert_eq!(sim(e, &lo, &[("b", *b)]).as_u64(), b & 0xF);
+			}
+			assert_eq!(var_value(e, "q"), RValue::Constant(BitVector::from_u64(1, 2)));
+		})
+	}
+
+	#[test]
+	fn namespaces() -> Result<(), CodegenError> {
+		let src = "
+			const unsigned<8> W = 3;
+			namespace cfg {
+				const unsigned<8> W = 8;
+				namespace inner {
+					const unsigned<8> X = W + 1;
+					struct pt { unsigned<4> a; unsigned<4> b; };
+				}
+			}
+			namespace cfg::inner {
+				typedef unsigned<X> word;
+			}
+		";
+		elaborate(&format!("{} block foo() -> () {{
+			auto a = cfg::W;
+			auto b = cfg::inner::X;
+			auto c = W;
+			cfg::inner::pt p = {{5, 6}};
+			cfg::inner::word w = 0x1FF;
+		}}", src), |e| {
+			assert_eq!(var_value(e, "a"), RValue::Constant(BitVector::from_u64(8, 8)));
+			assert_eq!(var_value(e, "b"), RValue::Constant(BitVector::from_u64(9, 8)));
+			assert_eq!(var_value(e, "c"), RValue::Constant(BitVector::from_u64(3, 8)));
+			assert_eq!(var_value(e, "w"), RValue::Constant(BitVector::from_u64(0x1FF, 9)));
+		})?;
+		// unqualified names are found in enclosing namespaces, innermost first
+		elaborate(&format!("{} namespace cfg {{ block foo(unsigned<8> X) -> () {{
+			auto a = W;
+			auto b = inner::X;
+			auto c = X;
+			inner::word w = 0;
+		}} }}", src), |e| {
+			assert_eq!(var_value(e, "a"), RValue::Constant(BitVector::from_u64(8, 8)));
+			assert_eq!(var_value(e, "b"), RValue::Constant(BitVector::from_u64(9, 8)));
+			assert!(matches!(var_value(e, "c"), RValue::Node(_)));
+		})
+	}
+
+	#[test]
+	fn functions() -> Result<(), CodegenError> {
+		let src = "
+			const unsigned<8> K = 5;
+			unsigned<8> add_k(unsigned<8> x, unsigned<8> y = K) { return x + y; }
+			unsigned<8> clamp(unsigned<8> x) {
+				if (x > 10) return 10;
+				return x;
+			}
+			void bump(unsigned<8>& x) { x += K; }
+			namespace util {
+				const unsigned<8> K = 2;
+				unsigned<8> twice(unsigned<8> x) { return x * K; }
+			}
+		";
+		elaborate(&format!("{} block foo(unsigned<8> a) -> () {{
+			auto b = add_k(1);
+			auto c = add_k(1, 2);
+			auto d = clamp(a);
+			unsigned<8> e = 1;
+			bump(e);
+			auto f = util::twice(3);
+			unsigned<8> g = 0;
+			if (a > 3) bump(g);
+		}}", src), |e| {
+			assert_eq!(var_value(e, "b"), RValue::Constant(BitVector::from_u64(6, 8)));
+			assert_eq!(var_value(e, "c"), RValue::Constant(BitVector::from_u64(3, 8)));
+			assert_eq!(var_value(e, "e"), RValue::Constant(BitVector::from_u64(6, 8)));
+			assert_eq!(var_value(e, "f"), RValue::Constant(BitVector::from_u64(6, 8)));
+			for a in 0..16 {
+				let d = var_value(e, "d");
+				assert_eq!(sim(e, &d, &[("a", a)]).as_u64(), std::cmp::min(a, 10));
+				let g = var_value(e, "g");
+				assert_eq!(sim(e, &g, &[("a", a)]).as_u64(), if a > 3 { 5 } else { 0 });
+			}
+		})?;
+		// the caller's variables aren't visible inside the function
+		let err = elaborate("unsigned<8> f() { return a; } block foo(unsigned<8> a) -> () { auto b = f(); }", |_| {});
+		assert!(err.unwrap_err().1.contains("unable to resolve variable"));
+		let err = elaborate("void f() { break; } block foo() -> () { for (int i = 0; i < 2; i++) f(); }", |_| {});
+		assert!(err.unwrap_err().1.contains("break outside"));
+		Ok(())
+	}
+
+	#[test]
+	fn constexpr_functions() -> Result<(), CodegenError> {
+		let src = "
+			unsigned<8> crc_step(unsigned<8> c) {
+				for (int i = 0; i < 8; i++) {
+					if (c & 0x80) c = (c << 1) ^ 0x07;
+					else c = c << 1;
+				}
+				return c;
+			}
+			unsigned<16> fact(unsigned<16> n) {
+				if (n == 0) return 1;
+				return n * fact(n - 1);
+			}
+			struct pair { unsigned<8> lo; unsigned<8> hi; };
+			pair split(unsigned<16> x) { pair p; p.lo = x; p.hi = x >> 8; return p; }
+			const unsigned<16> F5 = fact(5);
+		";
+		elaborate(&format!("{} block foo(unsigned<8> a) -> () {{
+			unsigned<8> table[4];
+			for (int i = 0; i < 4; i++) table[i] = crc_step(i);
+			unsigned<8> t3 = table[3];
+			unsigned<fact(3)> w = 0;
+			auto f5 = F5;
+			unsigned<16> r = 0;
+			if (a == 1) r = fact(4);
+			auto hi = split(0x1234).hi;
+		}}", src), |e| {
+			assert_eq!(var_value(e, "t3"), RValue::Constant(BitVector::from_u64(0x09, 8)));
+			assert_eq!(var_value(e, "w"), RValue::Constant(BitVector::from_u64(0, 6)));
+			assert_eq!(var_value(e, "f5"), RValue::Constant(BitVector::from_u64(120, 16)));
+			assert_eq!(var_value(e, "hi"), RValue::Constant(BitVector::from_u64(0x12, 8)));
+			for a in 0..4 {
+				let r = var_value(e, "r");
+				assert_eq!(sim(e, &r, &[("a", a)]).as_u64(), if a == 1 { 24 } else { 0 });
+			}
+		})?;
+		// recursion that never terminates at elaboration time is stopped; which needs more than the default test stack
+		let src = format!("{} block foo(unsigned<8> a) -> () {{ auto f = fact(a); }}", src);
+		let err = std::thread::Builder::new().stack_size(64 << 20).spawn(move || elaborate(&src, |_| {})).unwrap().join().unwrap();
+		assert!(err.unwrap_err().1.contains("maximum call depth"));
+		Ok(())
+	}
+
+	#[test]
+	fn static_assert() -> Result<(), CodegenError> {
+		let src = "
+			const unsigned<8> K = 4;
+			static_assert(K == 4, \"K changed\");
+			template <unsigned<8> N> struct fifo { static_assert(N > 0, \"depth must be positive\"); unsigned<8> data[N]; };
+			template <typename T> T first(T a) { fifo<0> f; return a; }
+		";
+		elaborate(&format!("{} block foo() -> () {{ fifo<2> f; static_assert(K > 1); }}", src), |_| {})?;
+		let err = elaborate(&format!("{} block foo() -> () {{ unsigned<8> x = first(3); }}", src), |_| {}).unwrap_err().1;
+		assert!(err.starts_with("static assertion failed: depth must be positive"));
+		// followed by the instantiations of fifo<0> and then first<T>
+		let lines: Vec<_> = err.lines().collect();
+		assert_eq!(lines.len(), 3);
+		assert!(lines[1..].iter().all(|l| l.starts_with("  in instantiation of")));
+		let err = elaborate("block foo() -> () { static_assert(1 > 2); }", |_| {}).unwrap_err().1;
+		assert!(err.contains("static assertion failed"));
+		Ok(())
+	}
+
+	#[test]
+	fn const_correctness() -> Result<(), CodegenError> {
+		let src = "
+			const unsigned<8> K = 4;
+			struct counter {
+				const unsigned<8> id;
+				unsigned<8> n;
+				unsigned<8> get() const { return n + id; }
+				void bump() { n += 1; }
+				void poke() const { n = 0; }
+			};
+			unsigned<8> sum(const unsigned<8>& x, const unsigned<8> y) { return x + y; }
+			void clear(const unsigned<8>& x) { x = 0; }
+		";
+		elaborate(&format!("{} block foo() -> () {{
+			counter c = {{1, 2}};
+			c.bump();
+			auto g = c.get();
+			const counter d = {{3, 4}};
+			auto h = d.get();
+			unsigned<8> s = sum(K, 2);
+			const unsigned<8> arr[2] = {{5, 6}};
+			unsigned<8> t = 0;
+			for (const auto& x : arr) t += x;
+		}}", src), |e| {
+			assert_eq!(var_value(e, "g"), RValue::Constant(BitVector::from_u64(4, 8)));
+			assert_eq!(var_value(e, "h"), RValue::Constant(BitVector::from_u64(7, 8)));
+			assert_eq!(var_value(e, "s"), RValue::Constant(BitVector::from_u64(6, 8)));
+			assert_eq!(var_value(e, "t"), RValue::Constant(BitVector::from_u64(11, 8)));
+		})?;
+		let err = |body: &str| elaborate(&format!("{} block foo() -> () {{ {} }}", src, body), |_| {}).unwrap_err().1;
+		assert!(err("K = 1;").contains("cannot assign to const variable"));
+		assert!(err("const unsigned<8> x = 1; x += 1;").contains("cannot assign to const variable"));
+		assert!(err("const unsigned<8> a[2] = {1, 2}; a[0] = 3;").contains("cannot assign to const variable"));
+		// pointing at the declaration of the member, not the variable
+		let member_err = err("counter c = {1, 2}; c.id = 3;");
+		assert!(member_err.contains("cannot assign to const member"));
+		assert!(member_err.ends_with(":4:5"));
+		assert!(member_err.contains("declared at `"));
+		assert!(err("const counter c = {1, 2}; c.n = 3;").contains("cannot assign to const variable"));
+		// through references, and the object of member functions
+		assert!(err("unsigned<8> a = 1; clear(a);").contains("cannot assign to const variable"));
+		assert!(err("counter c = {1, 2}; c.poke();").contains("cannot assign to const variable"));
+		assert!(err("const counter c = {1, 2}; c.bump();").contains("cannot assign to const variable"));
+		Ok(())
+	}
+
+	#[test]
+	fn undefined_values() -> Result<(), CodegenError> {
+		let warnings = |body: &str| {
+			let mut result = Vec::new();
+			elaborate(&format!("block foo(unsigned<8> a) -> (unsigned<8> b) {{ {} }}", body), |e| {
+				result = e.warnings.iter().map(|w| w.1.clone()).collect();
+			}).unwrap();
+			result
+		};
+		// assigned on only one branch and then read
+		let w = warnings("unsigned<8> x; if (a > 3) x = 1; b = x;");
+		assert_eq!(w.len(), 1);
+		assert!(w[0].contains("may be read while undefined") && w[0].contains("only assigned when"));
+		let w = warnings("unsigned<8> x; if (a > 3) { if (a > 5) x = 1; } else x = 2; b = x;");
+		assert_eq!(w.len(), 1);
+		assert_eq!(w[0].matches(" && ").count(), 1);
+		// fully assigned, initialised, or never read
+		assert!(warnings("unsigned<8> x; if (a > 3) x = 1; else x = 2; b = x;").is_empty());
+		assert!(warnings("unsigned<8> x; if (a > 3) { if (a > 5) x = 1; else x = 3; } else x = 2; b = x;").is_empty());
+		assert!(warnings("unsigned<8> x = 0; if (a > 3) x = 1; b = x;").is_empty());
+		assert!(warnings("unsigned<8> x; if (a > 3) x = 1; x = 2; b = x;").is_empty());
+		assert!(warnings("if (a > 3) { unsigned<8> t; if (a > 5) t = 1; } b = a;").is_empty());
+		// undefined values reaching an output
+		let w = warnings("if (a > 3) b = 1;");
+		assert_eq!(w.len(), 1);
+		assert!(w[0].contains("only assigned when"));
+		let w = warnings("");
+		assert_eq!(w.len(), 1);
+		assert!(w[0].contains("is not fully assigned"));
+		// undefined values reaching a register
+		elaborate("block foo(unsigned<8> a) -> () { unsigned<8> x; if (a > 3) x = a; unsigned<8> y = a + 1; }", |e| {
+			use crate::design::prim::Register;
+			for (i, var) in ["x", "y"].iter().enumerate() {
+				let d = match var_value(e, var) { RValue::Node(n) => n, v => panic!("expected node got {:?}", v) };
+				let name = e.st.ids.id(&format!("r{}", i));
+				let reg = e.st.des.add_prim(name, PrimitiveType::Reg(Register::Storage), SrcInfo::default()).unwrap();
+				e.st.des.add_prim_input(reg, constids::D, d).unwrap();
+				let q = e.st.ids.id(&format!("r{}_q", i));
+				e.st.des.add_node(q, OperandType::unsigned(8), SrcInfo::default(), reg, constids::Q).unwrap();
+			}
+			e.warnings.clear();
+			e.check_register_inputs();
+			assert_eq!(e.warnings.len(), 1);
+			assert!(e.warnings[0].1.contains("register r0 may be loaded with an undefined value through D"));
+		})?;
+		// outputs are driven by their final value
+		elaborate("block foo(unsigned<8> a) -> (unsigned<8> b) { b = a + 1; }", |e| {
+			let port = e.st.des.prims.named(e.st.ids.id("b")).unwrap();
+			assert_eq!(port.typ, PrimitiveType::TopPort);
+			let driver = RValue::Node(port.ports.named(constids::PORT).unwrap().node.unwrap());
+			for a in 0..4 {
+				assert_eq!(sim(e, &driver, &[("a", a)]).as_u64(), a + 1);
+			}
+			e.st.des.trim();
+			assert!(e.st.des.prims.named(e.st.ids.id("b")).is_some());
+		})
+	}
+
+	#[test]
+	fn attributes() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<8> a, unsigned<8> b) -> () {
+			[[keep]] unsigned<8> x = a + b;
+			[[max_fanout=4]] [[vendor_opt=3]] [[ram_style=distributed]] {
+				unsigned<8> y = a - b;
+				[[max_fanout=2]] unsigned<8> z = a & b;
+			}
+			unsigned<8> w = a | b;
+		}", |e| {
+			let attrs = |e: &mut Eval, op: BasicOp| {
+				let prim = e.st.des.prims.iter().find(|(_, p)| p.typ == PrimitiveType::BasicOp(op)).unwrap().1;
+				let mut attrs: Vec<_> = prim.attrs.iter().map(|(k, v)| (e.st.ids.get_str(*k).to_string(), v.clone())).collect();
+				attrs.sort_by(|a, b| a.0.cmp(&b.0));
+				attrs
+			};
+			let bits = |v, w| Constant::Bits(BitVector::from_u64(v, w));
+			assert_eq!(attrs(e, BasicOp::Add), vec![("keep".to_string(), bits(1, 1))]);
+			assert_eq!(attrs(e, BasicOp::Sub), vec![
+				("max_fanout".to_string(), bits(4, 64)),
+				("ram_style".to_string(), Constant::Str("distributed".to_string())),
+				("vendor_opt".to_string(), bits(3, 64)),
+			]);
+			// inner attributes override outer ones
+			assert_eq!(attrs(e, BasicOp::BwAnd)[0], ("max_fanout".to_string(), bits(2, 64)));
+			assert!(attrs(e, BasicOp::BwOr).is_empty());
+		})?;
+		// doc comments become attributes too
+		elaborate("block foo(unsigned<8> a) -> () {
+			/// the sum
+			unsigned<8> x = a + 1;
+		}
+		", |e| {
+			let prim = e.st.des.prims.iter().find(|(_, p)| p.typ == PrimitiveType::BasicOp(BasicOp::Add)).unwrap().1;
+			assert_eq!(prim.attrs.get(&constids::doc), Some(&Constant::Str("the sum".to_string())));
+		})?;
+		// unrelated unknown attributes reach the primitives, but a likely typo is an error
+		elaborate("block foo(unsigned<8> a) -> () { [[ep]] unsigned<8> y = a - 1; }", |e| {
+			let prim = e.st.des.prims.iter().find(|(_, p)| p.typ == PrimitiveType::BasicOp(BasicOp::Sub)).unwrap().1;
+			let attrs: Vec<_> = prim.attrs.keys().map(|k| e.st.ids.get_str(*k).to_string()).collect();
+			assert_eq!(attrs, vec!["ep".to_string()]);
+		})?;
+		let err = elaborate("block foo(unsigned<8> a) -> () { [[kep]] unsigned<8> x = a + 1; }", |_| {}).unwrap_err().1;
+		assert_eq!(err, "unknown attribute 'kep', did you mean 'keep'?");
+		let err = elaborate("block foo(unsigned<8> a) -> () { [[unroll=0]] for (int i = 0; i < 2; i++) {} }", |_| {}).unwrap_err().1;
+		assert!(err.contains("invalid value"));
+		Ok(())
+	}
+
+	#[test]
+	fn defines() -> Result<(), CodegenError> {
+		let src = "template <auto_int N> struct padded { unsigned<N + 1> v; };
+		const auto_int HALF = WIDTH / 2;
+		block foo(unsigned<8> a) -> () {
+			unsigned<WIDTH> x = a;
+			padded<HALF> p;
+			if meta (FAST) { auto y = a + 1; } else { auto y = a - 1; }
+		}";
+		elaborate_with_defines(src, &[("WIDTH", 32), ("FAST", 1)], |e| {
+			let width = |e: &mut Eval, name| var_value(e, name).to_type(&e.st).unwrap().pack(&e.st).unwrap().width;
+			assert_eq!(width(e, "x"), 32);
+			assert_eq!(width(e, "p"), 17);
+			assert!(e.st.des.prims.iter().any(|(_, p)| p.typ == PrimitiveType::BasicOp(BasicOp::Add)));
+		})?;
+		elaborate_with_defines(src, &[("WIDTH", 12), ("FAST", 0)], |e| {
+			assert_eq!(var_value(e, "x").to_type(&e.st).unwrap().pack(&e.st).unwrap().width, 12);
+			assert!(e.st.des.prims.iter().any(|(_, p)| p.typ == PrimitiveType::BasicOp(BasicOp::Sub)));
+		})?;
+		// a global of the same name is a default, overridden by the define but keeping its declared type
+		let src = "const auto WIDTH = 8;
+		const unsigned<16> DEPTH = 4;
+		namespace n { const auto WIDTH = 3; }
+		block foo(unsigned<8> a) -> () { unsigned<WIDTH> x = a; auto d = DEPTH; auto w = n::WIDTH; }";
+		elaborate_with_defines(src, &[("WIDTH", 32), ("DEPTH", 64)], |e| {
+			assert_eq!(var_value(e, "x").to_type(&e.st).unwrap().pack(&e.st).unwrap().width, 32);
+			assert_eq!(var_value(e, "d"), RValue::Constant(BitVector::from_u64(64, 16)));
+			assert_eq!(var_value(e, "w"), RValue::Constant(BitVector::from_u64(3, 64)));
+		})?;
+		elaborate_with_defines(src, &[], |e| {
+			assert_eq!(var_value(e, "x").to_type(&e.st).unwrap().pack(&e.st).unwrap().width, 8);
+		})?;
+		Ok(())
+	}
+
+	#[test]
+	fn error_locations() {
+		let err = elaborate("block foo(unsigned<8> a) -> () {
+			unsigned<8> x = a;
+			x = a[
+				9];
+		}", |_| {}).unwrap_err();
+		assert_eq!((err.0.start.line, err.0.start.col), (3, 8));
+		assert_eq!((err.0.end.line, err.0.end.col), (4, 7));
+	}
+
+	#[test]
+	fn char_string_literals() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<8> a) -> () {
+			auto c = 'A';
+			unsigned<8> nl = '\\n';
+			unsigned<24> s = \"abc\";
+			[[ram_style=\"block\"]] unsigned<8> x = a + c;
+		}", |e| {
+			assert_eq!(var_value(e, "c"), RValue::Constant(BitVector::from_u64(65, 8)));
+			assert_eq!(var_value(e, "nl"), RValue::Constant(BitVector::from_u64(10, 8)));
+			// first character in the lowest byte
+			assert_eq!(var_value(e, "s"), RValue::Constant(BitVector::from_u64(0x636261, 24)));
+			let prim = e.st.des.prims.iter().find(|(_, p)| p.typ == PrimitiveType::BasicOp(BasicOp::Add)).unwrap().1;
+			assert_eq!(prim.attrs.get(&e.st.ids.id("ram_style")), Some(&Constant::Str("block".to_string())));
+		})?;
+		Ok(())
+	}
+
+	#[test]
+	fn properties() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<8> a) -> () {
+			assume(a != 0);
+			if (a > 3) assert(a != 7);
+			assert(1);
+			if (0) assert(a == 1);
+		}", |e| {
+			let props: Vec<_> = e.st.des.prims.iter().filter(|(_, p)| p.typ.is_property()).map(|(i, _)| i).collect();
+			assert_eq!(props.len(), 2);
+			let port_value = |e: &mut Eval, port| RValue::Node(e.st.des.prims.get(props[1]).ports.named(port).unwrap().node.unwrap());
+			assert_eq!(e.st.des.prims.get(props[0]).typ, PrimitiveType::Assume);
+			assert_eq!(e.st.des.prims.get(props[1]).typ, PrimitiveType::Assert);
+			for a in 0..10 {
+				let (cond, enable) = (port_value(e, constids::A), port_value(e, constids::EN));
+				assert_eq!(sim(e, &cond, &[("a", a)]).as_bool(), a != 7);
+				assert_eq!(sim(e, &enable, &[("a", a)]).as_bool(), a > 3);
+			}
+			e.st.des.trim();
+			assert_eq!(e.st.des.prims.iter().filter(|(_, p)| p.typ.is_property()).count(), 2);
+		})?;
+		// a call with constant arguments is evaluated at elaboration time, but its properties are still only enabled
+		// under the caller's runtime conditions
+		elaborate("unsigned<8> checked(unsigned<8> x) { assert(x != 2); return x + 1; }
+		block foo(unsigned<8> a) -> () {
+			unsigned<8> r = 0;
+			if (a > 3) r = checked(2);
+		}", |e| {
+			let props: Vec<_> = e.st.des.prims.iter().filter(|(_, p)| p.typ.is_property()).map(|(i, _)| i).collect();
+			assert_eq!(props.len(), 1);
+			let port_value = |e: &mut Eval, port| RValue::Node(e.st.des.prims.get(props[0]).ports.named(port).unwrap().node.unwrap());
+			for a in 0..10 {
+				let (cond, enable) = (port_value(e, constids::A), port_value(e, constids::EN));
+				assert!(!sim(e, &cond, &[("a", a)]).as_bool());
+				assert_eq!(sim(e, &enable, &[("a", a)]).as_bool(), a > 3);
+				let r = var_value(e, "r");
+				assert_eq!(sim(e, &r, &[("a", a)]).as_u64(), if a > 3 { 3 } else { 0 });
+			}
+		})
+	}
+
+	#[test]
+	fn globals() -> Result<(), CodegenError> {
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let src = "
+			const unsigned<8> N = 3;
+			struct pt { unsigned<4> x; unsigned<4> y; };
+			block foo() -> () { pt p = {N, 1}; }
+			block bar() -> () { unsigned<8> n = N * 2; }";
+		let tok = Tokeniser::new(ids.id("<test>"), src.chars());
+		let ps = ParserState::new(tok, &mut ids).unwrap();
+		let mut p = Parser::new(ps);
+		let root = p.do_parse(&mut ids).unwrap();
+		// evaluated once, then used for every block
+		let globals = Eval::eval_globals(&mut ids, &root)?;
+		for (i, item) in root.content.iter().filter(|item| matches!(item, NamespaceItem::Stmt(st) if matches!(st.ty, StatementType::Module(_)))).enumerate() {
+			let m = match item { NamespaceItem::Stmt(Statement { ty: StatementType::Module(m), .. }) => m, _ => unreachable!() };
+			let mut e = Eval::init(&mut ids, m.name, &globals);
+			e.eval_mod(m)?;
+			if i == 0 {
+				assert!(var_value(&mut e, "p").is_fully_const());
+			} else {
+				assert_eq!(var_value(&mut e, "n"), RValue::Constant(BitVector::from_u64(6, 8)));
+			}
 		}
+		Ok(())
+	}
+
+	#[test]
+	fn decl_lists() -> Result<(), CodegenError> {
+		elaborate("const unsigned<8> K = 2, L = K + 1; block foo() -> () {
+			unsigned<8> a = L, b, c[2] = {4, 5};
+			int n = 0;
+			for (int i = 0, j = 3; i < j; i++) n += j;
+		}", |e| {
+			assert_eq!(var_value(e, "a"), RValue::Constant(BitVector::from_u64(3, 8)));
+			assert!(matches!(var_value(e, "b"), RValue::Constant(_)));
+			assert_eq!(var_value(e, "c"), RValue::Array(vec![RValue::Constant(BitVector::from_u64(4, 8)), RValue::Constant(BitVector::from_u64(5, 8))]));
+			assert!(matches!(var_value(e, "n"), RValue::Constant(c) if c.as_u64() == 9));
+		})
+	}
+
+	#[test]
+	fn range_for() -> Result<(), CodegenError> {
+		elaborate("block foo(unsigned<8> a) -> () {
+			unsigned<8> arr[4] = {1, 2, 3, 4};
+			unsigned<8> s = 0;
+			for meta (auto x : arr) s += x;
+			for (auto& x : arr) x *= 2;
+			unsigned<8> t = 0;
+			for (unsigned<8> i : range(3)) t += i;
+			for (int i : range(2, 5)) t += i;
+			unsigned<8> u = 0;
+			for (auto x : arr) { if (x > a) break; u += x; }
+		}", |e| {
+			assert_eq!(var_value(e, "s"), RValue::Constant(BitVector::from_u64(10, 8)));
+			assert_eq!(var_value(e, "arr"), RValue::Array([2, 4, 6, 8].iter().map(|i| RValue::Constant(BitVector::from_u64(*i, 8))).collect()));
+			assert_eq!(var_value(e, "t"), RValue::Constant(BitVector::from_u64(12, 8)));
+			for a in 0..10 {
+				let u = var_value(e, "u");
+				assert_eq!(sim(e, &u, &[("a", a)]).as_u64(), [2, 4, 6, 8].iter().take_while(|x| **x <= a).sum());
+			}
+		})?;
+		let err = elaborate("block foo() -> () { for (auto& x : range(4)) x++; }", |_| {});
+		assert!(err.unwrap_err().1.contains("requires an array variable"));
+		Ok(())
+	}
+
+	#[test]
+	fn inheritance() -> Result<(), CodegenError> {
+		let src = "
+			struct A { unsigned<4> x; unsigned<4> y; };
+			struct B : A { unsigned<8> z; };
+			interface I { input unsigned<1> valid; };
+			interface J : I { output unsigned<8> data; };
+		";
+		elaborate(&format!("{} block foo() -> () {{
+			B b = {{1, 2, 3}};
+			auto p = cat(b);
+			b.x = 5;
+			auto x = b.x;
+			J j;
+		}}", src), |e| {
+			// base members are packed into the least significant bits
+			assert_eq!(var_value(e, "p"), RValue::Constant(BitVector::from_u64(0x0321, 16)));
+			assert_eq!(var_value(e, "x"), RValue::Constant(BitVector::from_u64(5, 4)));
+			let valid = e.st.ids.id("valid");
+			let data = e.st.ids.id("data");
+			assert!(e.st.structs.values().any(|s| s.is_interface && s.members.iter().map(|(n, _)| *n).eq([valid, data].iter().copied())));
+		})?;
+		let err = elaborate(&format!("{} struct C : I {{ int a; }}; block foo() -> () {{ C c; }}", src), |_| {});
+		assert!(err.unwrap_err().1.contains("cannot inherit from interface"));
+		let err = elaborate(&format!("{} struct C : A {{ int y; }}; block foo() -> () {{ C c; }}", src), |_| {});
+		assert!(err.unwrap_err().1.contains("same name as an inherited member"));
+		Ok(())
+	}
+
+	#[test]
+	fn operator_overload() -> Result<(), CodegenError> {
+		let src = "
+			struct cplx {
+				unsigned<8> re;
+				unsigned<8> im;
+				cplx operator-() { return {0 - re, 0 - im}; }
+				unsigned<8> operator[](unsigned<1> i) { return i ? im : re; }
+				void scale(unsigned<8> k) { re *= k; im *= k; }
+				unsigned<8> sum() { return re + im; }
+			};
+			struct fix { unsigned<8> v; };
+			cplx operator+(cplx a, cplx b) { return {a.re + b.re, a.im + b.im}; }
+			fix operator+(fix a, fix b) { return {a.v + b.v + 1}; }
+			unsigned<1> operator==(cplx a, cplx b) { return a.re == b.re && a.im == b.im; }
+		";
+		elaborate(&format!("{} block foo(unsigned<8> a) -> () {{
+			cplx x = {{1, 2}};
+			cplx y = {{a, 3}};
+			cplx z = x + y;
+			auto zr = z.re;
+			auto zi = z[1];
+			cplx n = -x;
+			auto nr = n.re;
+			x.scale(3);
+			auto s = x.sum();
+			x += x;
+			auto xr = x.re;
+			fix f = {{4}};
+			fix g = f + f;
+			auto gv = g.v;
+			auto eq = x == x;
+		}}", src), |e| {
+			for a in 0..4 {
+				let zr = var_value(e, "zr");
+				assert_eq!(sim(e, &zr, &[("a", a)]).as_u64(), a + 1);
+			}
+			assert_eq!(var_value(e, "zi"), RValue::Constant(BitVector::from_u64(5, 8)));
+			assert_eq!(var_value(e, "nr"), RValue::Constant(BitVector::from_u64(255, 8)));
+			assert_eq!(var_value(e, "s"), RValue::Constant(BitVector::from_u64(9, 8)));
+			assert_eq!(var_value(e, "xr"), RValue::Constant(BitVector::from_u64(6, 8)));
+			assert_eq!(var_value(e, "gv"), RValue::Constant(BitVector::from_u64(9, 8)));
+			assert_eq!(var_value(e, "eq"), RValue::Constant(BitVector::from_u64(1, 1)));
+		})?;
+		let err = elaborate(&format!("{} block foo() -> () {{ fix f = {{1}}; auto g = f * f; }}", src), |_| {});
+		assert!(err.unwrap_err().1.contains("no operator*"));
+		let err = elaborate(&format!("{} block foo() -> () {{ fix f = {{1}}; auto h = f[0]; }}", src), |_| {});
+		assert!(err.unwrap_err().1.contains("no operator[]"));
+		Ok(())
+	}
+
+	#[test]
+	fn templates() -> Result<(), CodegenError> {
+		let src = "
+			template <typename T, unsigned<8> N = 2> struct vec { T x[N]; };
+			template <typename T> struct vec<T, 1> { T only; };
+			template <> struct vec<unsigned<4>, 1> { unsigned<4> a; unsigned<4> b; };
+			template <typename T> T twice(T a) { return a + a; }
+			template <> unsigned<4> twice<unsigned<4> >(unsigned<4> a) { return 0; }
+			template <unsigned<8> W, typename T = unsigned<W> > T trunc(unsigned<8> a) { return a; }
+			unsigned<8> twice(unsigned<8> a, unsigned<8> b) { return a + b; }
+		";
+		elaborate(&format!("{} template <unsigned<8> W = 3> block foo(unsigned<8> a) -> () {{
+			vec<unsigned<8> > v = {{{{1, 2}}}};
+			auto v1 = v.x[1];
+			vec<unsigned<8>, 1> p = {{7}};
+			auto po = p.only;
+			vec<unsigned<4>, 1> q = {{1, 2}};
+			auto qb = q.b;
+			unsigned<6> six = 5;
+			auto t6 = twice(six);
+			unsigned<4> four = 5;
+			auto t4 = twice(four);
+			auto t8 = twice(a, a);
+			auto tr = trunc<W>(0xFF);
+		}}", src), |e| {
+			assert_eq!(var_value(e, "v1"), RValue::Constant(BitVector::from_u64(2, 8)));
+			assert_eq!(var_value(e, "po"), RValue::Constant(BitVector::from_u64(7, 8)));
+			assert_eq!(var_value(e, "qb"), RValue::Constant(BitVector::from_u64(2, 4)));
+			assert_eq!(var_value(e, "t6"), RValue::Constant(BitVector::from_u64(10, 6)));
+			assert_eq!(var_value(e, "t4"), RValue::Constant(BitVector::from_u64(0, 4)));
+			assert_eq!(var_value(e, "tr"), RValue::Constant(BitVector::from_u64(7, 3)));
+			for a in 0..4 {
+				let t8 = var_value(e, "t8");
+				assert_eq!(sim(e, &t8, &[("a", a)]).as_u64(), a * 2);
+			}
+		})
 	}
 }
@@ -3,8 +3,10 @@ pub mod state;
 pub mod value;
 pub mod ident;
 pub mod eval;
+pub mod attrs;
 
-pub use value::{RValue, ValuePathItem, Variable, LValue};
+pub use value::{RValue, StructureValue, UnionValue, ValuePathItem, Variable, LValue};
 pub use ident::Identifier;
-pub use resolved_type::{ResolvedKey, ResolvedType, ResolvedTypes, DerivedStruct};
-pub use state::{GenState, GenScope, CodegenError};
\ No newline at end of file
+pub use resolved_type::{ResolvedArg, ResolvedKey, ResolvedType, ResolvedTypes, DerivedStruct};
+pub use state::{GenState, GenScope, Globals, StructDecl, FuncDecl, CodegenError, CodegenWarning};
+pub use eval::Eval;
\ No newline at end of file
@@ -1,8 +1,7 @@
-use crate::ast::{DataType, Function};
-use rustc_hash::FxHashMap;
+use crate::ast::{DataType, SrcInfo, StructureDef};
 use crate::core::IdString;
 use crate::core::{BitVector, OperandType};
-use crate::codegen::{CodegenError, Identifier};
+use crate::codegen::{CodegenError, GenState, Identifier, FuncDecl, StructDecl};
 use crate::codegen::eval::Eval;
 use std::fmt;
 
@@ -118,35 +117,203 @@ impl ResolvedType {
 		use ResolvedTypes::*;
 		let base_type = match &dt.typ {
 			DataTypes::Void => ResolvedType {typ: Void, is_static: false, is_const: false},
+			DataTypes::AutoInt => ResolvedType {typ: AutoInt, is_static: false, is_const: false},
 			DataTypes::Integer(i) => {
 				let width = e.const_eval_scalar(&i.width)?.as_u64() as usize;
 				let is_signed = e.const_eval_scalar(&i.is_signed)?.as_u64() != 0;
 				ResolvedType {typ: Integer(OperandType::new(width, is_signed)), is_static: false, is_const: false}
 			},
+			DataTypes::User(ut) => {
+				if let Some(typ) = e.st.lookup_type(ut.name) {
+					if !ut.args.is_empty() {
+						return Err(CodegenError(SrcInfo::default(), format!("type {:?} does not take template arguments", ut.name)));
+					}
+					typ.clone()
+				} else if let Some(decl) = e.st.lookup_struct(ut.name) {
+					let decl = decl.clone();
+					let args = e.resolve_templ_vals(&ut.args)?;
+					let key = DerivedStruct::derive(e, &decl, &args)?;
+					ResolvedType {typ: Struct(key), is_static: false, is_const: false}
+				} else {
+					return Err(CodegenError(SrcInfo::default(), format!("unable to resolve type {:?}", ut.name)));
+				}
+			},
+			DataTypes::Reference(r) => {
+				let target = ResolvedType::do_resolve(e, r)?;
+				ResolvedType {typ: Reference(Box::new(target)), is_static: false, is_const: false}
+			},
+			DataTypes::Array(a) => {
+				// int x[A][B] is an array of A arrays of B ints
+				let mut typ = ResolvedType::do_resolve(e, &a.base)?;
+				for dim in a.dims.iter().rev() {
+					let count = e.const_eval_scalar(dim)?.as_u64() as usize;
+					typ = ResolvedType {typ: Array(Box::new(typ), count), is_static: false, is_const: false};
+				}
+				typ
+			},
 			_ => unimplemented!()
 		};
-		Ok(base_type)
+		Ok(ResolvedType {
+			is_const: base_type.is_const | dt.is_const,
+			is_static: base_type.is_static | dt.is_static,
+			typ: base_type.typ,
+		})
 	}
-	pub fn pack(&self) -> Result<OperandType, CodegenError> {
-		match self.typ {
+	// Width of the type once flattened into a bitvector.
+	// Structures are packed with the first member in the least significant bits; and union members all start at bit 0
+	pub fn pack(&self, st: &GenState) -> Result<OperandType, CodegenError> {
+		match &self.typ {
 			ResolvedTypes::Void => Ok(OperandType::new(0, false)),
-			ResolvedTypes::Integer(i) => Ok(i),
-			_ => unimplemented!(),
+			ResolvedTypes::Integer(i) => Ok(*i),
+			ResolvedTypes::Array(base, count) => Ok(OperandType::unsigned(base.pack(st)?.width * count)),
+			ResolvedTypes::Struct(key) => {
+				let data = st.structs.get(key).ok_or_else(|| CodegenError(SrcInfo::default(), format!("unknown structure {:?}", key)))?;
+				let mut width = 0;
+				for (_, mt) in data.members.iter() {
+					let member_width = mt.pack(st)?.width;
+					width = if data.is_union { std::cmp::max(width, member_width) } else { width + member_width };
+				}
+				Ok(OperandType::unsigned(width))
+			},
+			_ => Err(CodegenError(SrcInfo::default(), format!("type {:?} cannot be packed", self))),
 		}
 	}
 }
 
 // A derived structure
+#[derive(Clone)]
 pub struct DerivedStruct {
-	pub members: FxHashMap<IdString, ResolvedType>,
-	pub functions: Vec<Function>, 
+	pub members: Vec<(IdString, ResolvedType)>,
+	// Where each member was declared, in the same order as members
+	pub member_src: Vec<SrcInfo>,
+	// Member functions, which are elaborated with the template arguments of the structure
+	pub functions: Vec<FuncDecl>,
+	pub is_union: bool,
+	pub is_interface: bool,
 }
 
 impl DerivedStruct {
 	pub fn new() -> DerivedStruct {
 		DerivedStruct {
-			members: FxHashMap::default(),
+			members: Vec::new(),
+			member_src: Vec::new(),
 			functions: Vec::new(),
+			is_union: false,
+			is_interface: false,
+		}
+	}
+	pub fn member(&self, name: IdString) -> Option<&ResolvedType> {
+		self.members.iter().find(|(n, _)| *n == name).map(|(_, t)| t)
+	}
+	pub fn member_decl(&self, name: IdString) -> Option<SrcInfo> {
+		self.members.iter().position(|(n, _)| *n == name).map(|i| self.member_src[i])
+	}
+	// Offset of a member in the packed representation of the structure
+	pub fn member_offset(&self, st: &GenState, name: IdString) -> Result<usize, CodegenError> {
+		if self.is_union {
+			return Ok(0);
 		}
+		let mut offset = 0;
+		for (n, t) in self.members.iter() {
+			if *n == name {
+				return Ok(offset);
+			}
+			offset += t.pack(st)?.width;
+		}
+		Err(CodegenError(SrcInfo::default(), format!("no member named {:?}", name)))
+	}
+	// Create the derived version of a structure for some template arguments, if it doesn't exist already. Names in the
+	// structure are resolved in the namespace it was declared in, with its template parameters bound
+	pub fn derive(e: &mut Eval, decl: &StructDecl, args: &[ResolvedArg]) -> Result<ResolvedKey, CodegenError> {
+		e.in_templ_scope(&decl.ns_path, &[], |e| Self::derive_templ(e, decl, args))
+	}
+	fn derive_templ(e: &mut Eval, decl: &StructDecl, args: &[ResolvedArg]) -> Result<ResolvedKey, CodegenError> {
+		let sd = &decl.def;
+		// missing arguments take their defaults
+		let given: Vec<_> = args.iter().cloned().map(Some).collect();
+		let bindings = e.bind_templ_args(sd.src, &sd.templ_args, &given)?
+			.ok_or_else(|| CodegenError(sd.src, format!("missing template arguments for structure {:?}", sd.name)))?;
+		let key = ResolvedKey { name: decl.ident.clone(), templ_args: bindings.iter().map(|(_, a)| a.clone()).collect() };
+		if e.st.structs.contains_key(&key) {
+			return Ok(key);
+		}
+		let specs: Vec<_> = decl.specs.iter().map(|s| (&s.templ_args[..], &s.specialisation.as_ref().unwrap()[..])).collect();
+		let spec = e.best_spec(sd.src, &specs, &key.templ_args)?;
+		let data = e.in_instantiation(sd.name, &bindings, |e| match spec {
+			// a specialisation only sees its own template parameters
+			Some((i, spec_bindings)) => e.in_templ_scope(&decl.ns_path, &spec_bindings, |e| Self::derive_data(e, &decl.ns_path, &decl.specs[i], &spec_bindings)),
+			None => Self::derive_data(e, &decl.ns_path, sd, &bindings),
+		})?;
+		e.st.structs.insert(key.clone(), data);
+		Ok(key)
+	}
+	fn derive_data(e: &mut Eval, ns_path: &[IdString], sd: &StructureDef, templ: &[(IdString, ResolvedArg)]) -> Result<DerivedStruct, CodegenError> {
+		use crate::ast::StatementType;
+		let mut data = DerivedStruct::new();
+		data.is_union = sd.is_union;
+		data.is_interface = sd.is_interface;
+		// members of base types come first, so they are in the least significant bits when packed
+		for base in sd.bases.iter() {
+			let base_data = match ResolvedType::do_resolve(e, base)?.typ {
+				ResolvedTypes::Struct(base_key) => e.st.structs.get(&base_key).unwrap().clone(),
+				_ => return Err(CodegenError(sd.src, format!("{:?} can only inherit from a structure or interface, not {}", sd.name, base))),
+			};
+			if base_data.is_union {
+				return Err(CodegenError(sd.src, format!("{:?} cannot inherit from union {}", sd.name, base)));
+			}
+			if base_data.is_interface != sd.is_interface {
+				return Err(CodegenError(sd.src, format!("{} {:?} cannot inherit from {} {}",
+					if sd.is_interface { "interface" } else { "structure" }, sd.name, if base_data.is_interface { "interface" } else { "structure" }, base)));
+			}
+			for ((name, typ), src) in base_data.members.into_iter().zip(base_data.member_src) {
+				if data.member(name).is_some() {
+					return Err(CodegenError(sd.src, format!("member {:?} inherited by {:?} more than once", name, sd.name)));
+				}
+				data.members.push((name, typ));
+				data.member_src.push(src);
+			}
+			data.functions.extend(base_data.functions.into_iter());
+		}
+		let base_count = data.members.len();
+		let inherited_funcs = std::mem::take(&mut data.functions);
+		let body = match &sd.block.ty {
+			StatementType::Block(b) => &b[..],
+			_ => std::slice::from_ref(sd.block.as_ref()),
+		};
+		for st in body.iter() {
+			match &st.ty {
+				StatementType::Var(_) | StatementType::VarList(_) => {
+					let decls = match &st.ty {
+						StatementType::VarList(vs) => &vs[..],
+						StatementType::Var(v) => std::slice::from_ref(v),
+						_ => unreachable!(),
+					};
+					for v in decls.iter() {
+						let typ = ResolvedType::do_resolve(e, &v.ty)?;
+						if data.is_union {
+							typ.pack(&e.st).map_err(|err| CodegenError(st.src, format!("union member {:?} cannot be packed: {}", v.name, err.1)))?;
+						}
+						data.members.push((v.name, typ));
+						data.member_src.push(st.src);
+					}
+				},
+				StatementType::InterfacePort(p) => {
+					let typ = ResolvedType::do_resolve(e, &p.arg_type)?;
+					data.members.push((p.name, typ));
+					data.member_src.push(st.src);
+				},
+				StatementType::Func(f) => data.functions.push(FuncDecl { ns_path: ns_path.to_vec(), func: f.clone(), templ: templ.to_vec() }),
+				StatementType::Null => {},
+				_ => e.eval_st(st)?,
+			}
+		}
+		if let Some((name, _)) = data.members[base_count..].iter().find(|(n, _)| data.members[..base_count].iter().any(|(b, _)| b == n)) {
+			return Err(CodegenError(sd.src, format!("member {:?} of {:?} has the same name as an inherited member", name, sd.name)));
+		}
+		// member functions hide any inherited functions with the same name
+		let mut functions: Vec<_> = inherited_funcs.into_iter().filter(|f| !data.functions.iter().any(|df| df.func.name == f.func.name)).collect();
+		functions.append(&mut data.functions);
+		data.functions = functions;
+		Ok(data)
 	}
-}
\ No newline at end of file
+}
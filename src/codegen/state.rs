@@ -1,14 +1,19 @@
-use crate::ast::{SrcInfo, Function};
-use crate::core::{BitVector, State, StoreIndex, IdString, IdStringDb, ObjectStore, NullableIndex, constids};
+use crate::ast::{SrcInfo, Function, StructureDef};
+use crate::core::{BitVector, Constant, State, StoreIndex, IdString, IdStringDb, ObjectStore, NullableIndex, OperandType, SSOArray, constids};
 use rustc_hash::FxHashMap;
 
-use crate::design::{Node, Design, PrimitiveType};
+use crate::design::{Node, Design, Primitive, PrimitiveType, SpecialOperation};
 
 use crate::codegen::*;
+use crate::codegen::ident::IdentPart;
 
 #[derive(Eq, PartialEq, Debug)]
 pub struct CodegenError(pub SrcInfo, pub String);
 
+// Something suspicious about the design that doesn't stop elaboration
+#[derive(Eq, PartialEq, Debug)]
+pub struct CodegenWarning(pub SrcInfo, pub String);
+
 // Codegen state for the elaboration of a module
 pub struct GenState<'a> {
 	// The current IdString database
@@ -24,49 +29,89 @@ pub struct GenState<'a> {
 	scopes: Vec<GenScope>,
 	// The current stack of conditionals to be applied by the evaluator
 	conds: Vec<(StoreIndex<Node>, bool)>,
+	// The namespace currently being elaborated; declarations in the outermost scope are qualified by this
+	pub ns_path: Vec<IdString>,
+	// The first scope visible to the function being elaborated; scopes before this belong to its callers
+	pub frame: usize,
+	// Attributes of the statements and expressions being elaborated, outermost first; given to every primitive created
+	pub attrs: Vec<(IdString, Constant)>,
 	auto_idx: usize,
+	scope_idx: usize,
 }
 
 impl <'a> GenState<'a> {
-	pub fn new(ids: &'a mut IdStringDb, name: IdString) -> Self {
+	pub fn new(ids: &'a mut IdStringDb, name: IdString, globals: &Globals) -> Self {
+		let mut root = globals.scope.clone();
+		root.name = IdentPart::Str(name);
 		Self {
 			ids: ids,
-			vars: ObjectStore::new(),
+			vars: globals.vars.clone(),
 			funcs: FxHashMap::default(),
-			structs: FxHashMap::default(),
+			structs: globals.structs.clone(),
 			des: Design::new(name),
-			scopes: vec![GenScope::new(0)],
+			scopes: vec![root],
 			conds: Vec::new(),
+			ns_path: Vec::new(),
+			frame: 1,
+			attrs: Vec::new(),
 			auto_idx: 0,
+			scope_idx: 0,
 		}
 	} 
+	// Extract the namespace level declarations, once they have been elaborated
+	pub fn into_globals(self) -> Globals {
+		Globals {
+			vars: self.vars,
+			structs: self.structs,
+			scope: self.scopes.into_iter().next().unwrap(),
+		}
+	}
 	pub fn next_name(&mut self, base: IdString) -> IdString {
 		self.auto_idx += 1;
 		let new_name = format!("{}${}$", self.ids.get_str(base), self.auto_idx);
 		self.ids.id(&new_name)
 	}
+	// Create a primitive, with the attributes currently being applied
+	pub fn add_prim(&mut self, name: IdString, typ: PrimitiveType, src: SrcInfo) -> StoreIndex<Primitive> {
+		let prim = self.des.add_prim(name, typ, src).unwrap();
+		self.apply_attrs(prim);
+		prim
+	}
+	fn apply_attrs(&mut self, prim: StoreIndex<Primitive>) {
+		// inner attributes override outer ones of the same name
+		for (name, value) in self.attrs.iter() {
+			self.des.prims.get_mut(prim).attrs.insert(*name, value.clone());
+		}
+	}
 	pub fn get_node(&mut self, value: &RValue, src: SrcInfo) -> StoreIndex<Node> {
 		match value {
 			RValue::Node(n) => *n,
-			RValue::Constant(c) => self.des.add_const(self.ids, c.clone(), src),
+			RValue::Constant(c) => {
+				let node = self.des.add_const(self.ids, c.clone(), src);
+				self.apply_attrs(self.des.nodes.get(node).driver.prim);
+				node
+			},
 			_ => unreachable!(),
 		}
 	}
-	pub fn apply_conditionals(&mut self, base_name: IdString, old_value: RValue, new_value: RValue, src: SrcInfo) -> RValue {
-		if self.conds.is_empty() {
+	// Conditions before cond_idx were pushed before the variable being assigned was declared, so it can't be used
+	// where they don't hold and they don't need to be applied
+	pub fn apply_conditionals(&mut self, base_name: IdString, cond_idx: usize, old_value: RValue, new_value: RValue, src: SrcInfo) -> RValue {
+		let conds = self.conds[cond_idx.min(self.conds.len())..].to_vec();
+		if conds.is_empty() {
 			return new_value;
 		}
-		let mut cond_inv = BitVector::new(self.conds.len(), false);
-		for (i, (_, inv)) in self.conds.iter().enumerate() {
+		let mut cond_inv = BitVector::new(conds.len(), false);
+		for (i, (_, inv)) in conds.iter().enumerate() {
 			cond_inv.set(i, if *inv { State::S1 } else { State::S0 });
 		}
 		let prim_name = self.next_name(base_name);
-		let prim = self.des.add_prim(prim_name, PrimitiveType::Cond { inv: cond_inv}, src).unwrap();
+		let prim = self.add_prim(prim_name, PrimitiveType::Cond { inv: cond_inv}, src);
 		let old_node = self.get_node(&old_value, src);
 		let new_node = self.get_node(&new_value, src);
 		self.des.add_prim_input(prim, constids::A, old_node).unwrap();
 		self.des.add_prim_input(prim, constids::B, new_node).unwrap();
-		for (i, (node, _)) in self.conds.iter().enumerate() {
+		for (i, (node, _)) in conds.iter().enumerate() {
 			let port_name = self.ids.id(&format!("S{}", i));
 			self.des.add_prim_input(prim, port_name, *node).unwrap();
 		}
@@ -74,39 +119,326 @@ impl <'a> GenState<'a> {
 		let node_name = self.next_name(base_name);
 		RValue::from_node(self.des.add_node(node_name, typ, src, prim, constids::Q).unwrap())
 	}
-	pub fn assign_variable(&mut self, var: StoreIndex<Variable>, path: &[ValuePathItem], new_value: &RValue, src: SrcInfo) {
-		let curr_value = self.vars.get(var).value.get(path).clone();
-		if curr_value.is_scalar() {
-			// at the end of the line, actually assign the value
-			assert!(new_value.is_scalar());
-			let value_name = self.vars.get(var).name;
-			let applied_value = self.apply_conditionals(value_name, curr_value.clone(), new_value.clone(), src);
-			self.vars.get_mut(var).value.set(path, applied_value);
-		} else {
-			match new_value {
-				RValue::Array(values) => {
-					for (i, val) in values.iter().enumerate() {
-						let mut next_path = Vec::from(path);
-						next_path.push(ValuePathItem::ConstIndex(i));
-						self.assign_variable(var, &next_path, val, src);
+	// Select between two values based on a runtime condition; scalars are extended to a common type
+	pub fn mux_value(&mut self, cond: StoreIndex<Node>, if_false: &RValue, if_true: &RValue, src: SrcInfo) -> Result<RValue, CodegenError> {
+		if if_false == if_true {
+			return Ok(if_true.clone());
+		}
+		let base_name = self.des.auto_id(self.ids);
+		if let (Some(ft), Some(tt)) = (self.scalar_type(if_false), self.scalar_type(if_true)) {
+			let typ = OperandType { width: std::cmp::max(ft.width, tt.width), is_signed: ft.is_signed && tt.is_signed };
+			let f = self.convert_scalar(if_false, typ, src);
+			let f = self.get_node(&f, src);
+			let t = self.convert_scalar(if_true, typ, src);
+			let t = self.get_node(&t, src);
+			let sel = self.ids.id("S0");
+			return Ok(self.add_op_prim(base_name, PrimitiveType::Cond { inv: BitVector::from_u64(0, 1) }, &[(constids::A, f), (constids::B, t), (sel, cond)], typ, src));
+		}
+		// aggregates of the same type are muxed in their packed form
+		let typ = if_true.to_type(self).unwrap();
+		if if_false.to_type(self).as_ref() != Some(&typ) {
+			return Err(CodegenError(src, format!("incompatible values {:?} and {:?} in conditional expression", if_false, if_true)));
+		}
+		let f = self.pack_value(if_false, &typ, src)?;
+		let t = self.pack_value(if_true, &typ, src)?;
+		let packed = self.mux_value(cond, &f, &t, src)?;
+		self.unpack_value(&packed, &typ, src)
+	}
+	// Create a single-output primitive, returning its output as a value
+	pub fn add_op_prim(&mut self, base_name: IdString, typ: PrimitiveType, inputs: &[(IdString, StoreIndex<Node>)], out_type: OperandType, src: SrcInfo) -> RValue {
+		let prim_name = self.next_name(base_name);
+		let prim = self.add_prim(prim_name, typ, src);
+		for (port, node) in inputs.iter() {
+			self.des.add_prim_input(prim, *port, *node).unwrap();
+		}
+		let node_name = self.next_name(base_name);
+		RValue::from_node(self.des.add_node(node_name, out_type, src, prim, constids::Q).unwrap())
+	}
+	pub fn scalar_type(&self, value: &RValue) -> Option<OperandType> {
+		match value {
+			RValue::Constant(c) => Some(c.op_type()),
+			RValue::Node(n) => Some(self.des.nodes.get(*n).typ),
+			_ => None,
+		}
+	}
+	// Truncate, extend or change the signedness of a scalar
+	pub fn convert_scalar(&mut self, value: &RValue, typ: OperandType, src: SrcInfo) -> RValue {
+		match value {
+			RValue::Constant(c) => RValue::Constant(c.resize(typ.width, typ.is_signed)),
+			RValue::Node(n) => {
+				if self.des.nodes.get(*n).typ == typ {
+					value.clone()
+				} else {
+					let base_name = self.des.nodes.get(*n).name;
+					self.add_op_prim(base_name, PrimitiveType::SpecOp(SpecialOperation::Resize(typ.width)), &[(constids::A, *n)], typ, src)
+				}
+			},
+			_ => unreachable!(),
+		}
+	}
+	// Extract bits from a scalar, reinterpreting them as the given type
+	pub fn slice_get(&mut self, value: &RValue, offset: usize, typ: OperandType, src: SrcInfo) -> RValue {
+		match value {
+			RValue::Constant(c) => {
+				let mut sliced = c.slice(offset, typ.width);
+				sliced.is_signed = typ.is_signed;
+				RValue::Constant(sliced)
+			}
+			RValue::Node(n) => {
+				let node = self.des.nodes.get(*n);
+				if offset == 0 && node.typ == typ {
+					return value.clone();
+				}
+				let base_name = node.name;
+				self.add_op_prim(base_name, PrimitiveType::SpecOp(SpecialOperation::SliceGetFix { offset: offset, width: typ.width }), &[(constids::A, *n)], typ, src)
+			},
+			_ => unreachable!(),
+		}
+	}
+	// Replace bits of a scalar
+	pub fn slice_set(&mut self, base: &RValue, offset: usize, value: &RValue, src: SrcInfo) -> RValue {
+		let base_type = self.scalar_type(base).unwrap();
+		let width = self.scalar_type(value).unwrap().width;
+		if offset == 0 && width == base_type.width {
+			return self.convert_scalar(value, base_type, src);
+		}
+		match (base, value) {
+			(RValue::Constant(b), RValue::Constant(v)) => {
+				let mut result = b.clone();
+				result.set_slice(offset, v);
+				RValue::Constant(result)
+			},
+			_ => {
+				let base_node = self.get_node(base, src);
+				let value_node = self.get_node(value, src);
+				let base_name = self.des.nodes.get(base_node).name;
+				self.add_op_prim(base_name, PrimitiveType::SpecOp(SpecialOperation::SliceSetFix { offset: offset, width: width }),
+					&[(constids::A, base_node), (constids::B, value_node)], base_type, src)
+			}
+		}
+	}
+	// Concatenate two scalars, with lo in the least significant bits
+	pub fn concat(&mut self, hi: &RValue, lo: &RValue, src: SrcInfo) -> RValue {
+		let lo_width = self.scalar_type(lo).unwrap().width;
+		let typ = OperandType::unsigned(lo_width + self.scalar_type(hi).unwrap().width);
+		match (hi, lo) {
+			(RValue::Constant(h), RValue::Constant(l)) => {
+				let mut result = l.resize(typ.width, false);
+				result.set_slice(lo_width, h);
+				RValue::Constant(result)
+			},
+			_ => {
+				let hi_node = self.get_node(hi, src);
+				let lo_node = self.get_node(lo, src);
+				let base_name = self.des.auto_id(self.ids);
+				self.add_op_prim(base_name, PrimitiveType::SpecOp(SpecialOperation::Cat), &[(constids::A, lo_node), (constids::B, hi_node)], typ, src)
+			}
+		}
+	}
+	// Flatten a value into a single unsigned scalar
+	pub fn pack_value(&mut self, value: &RValue, typ: &ResolvedType, src: SrcInfo) -> Result<RValue, CodegenError> {
+		let width = typ.pack(self)?.width;
+		match (&typ.typ, value) {
+			(ResolvedTypes::Integer(_), v) if v.is_scalar() => Ok(self.convert_scalar(v, OperandType::unsigned(width), src)),
+			(ResolvedTypes::Struct(_), RValue::Union(uv)) => Ok(self.convert_scalar(&uv.bits, OperandType::unsigned(width), src)),
+			(ResolvedTypes::Struct(key), RValue::Structure(sv)) => {
+				let members = self.structs.get(key).unwrap().members.clone();
+				let mut result = RValue::Constant(BitVector::new(width, false));
+				let mut offset = 0;
+				for (name, mt) in members.iter() {
+					let packed = self.pack_value(&sv.values[name], mt, src)?;
+					result = self.slice_set(&result, offset, &packed, src);
+					offset += mt.pack(self)?.width;
+				}
+				Ok(result)
+			},
+			(ResolvedTypes::Array(base, _), RValue::Array(vals)) => {
+				let elem_width = base.pack(self)?.width;
+				let mut result = RValue::Constant(BitVector::new(width, false));
+				for (i, v) in vals.iter().enumerate() {
+					let packed = self.pack_value(v, base, src)?;
+					result = self.slice_set(&result, i * elem_width, &packed, src);
+				}
+				Ok(result)
+			},
+			(ResolvedTypes::Void, _) => Ok(RValue::Constant(BitVector::new(0, false))),
+			_ => Err(CodegenError(src, format!("unable to pack {:?} as type {:?}", value, typ))),
+		}
+	}
+	// Reinterpret a scalar as a value of the given type
+	pub fn unpack_value(&mut self, packed: &RValue, typ: &ResolvedType, src: SrcInfo) -> Result<RValue, CodegenError> {
+		match &typ.typ {
+			ResolvedTypes::Integer(it) => Ok(self.slice_get(packed, 0, *it, src)),
+			ResolvedTypes::Struct(key) => {
+				let width = typ.pack(self)?.width;
+				let (members, is_union) = {
+					let data = self.structs.get(key).unwrap();
+					(data.members.clone(), data.is_union)
+				};
+				if is_union {
+					return Ok(RValue::Union(UnionValue { typ: key.clone(), bits: Box::new(self.slice_get(packed, 0, OperandType::unsigned(width), src)) }));
+				}
+				let mut values = FxHashMap::default();
+				let mut offset = 0;
+				for (name, mt) in members.iter() {
+					let member_width = mt.pack(self)?.width;
+					let bits = self.slice_get(packed, offset, OperandType::unsigned(member_width), src);
+					values.insert(*name, self.unpack_value(&bits, mt, src)?);
+					offset += member_width;
+				}
+				Ok(RValue::Structure(StructureValue { typ: key.clone(), values: values }))
+			},
+			ResolvedTypes::Array(base, count) => {
+				let elem_width = base.pack(self)?.width;
+				let mut values = Vec::new();
+				for i in 0..*count {
+					let bits = self.slice_get(packed, i * elem_width, OperandType::unsigned(elem_width), src);
+					values.push(self.unpack_value(&bits, base, src)?);
+				}
+				Ok(RValue::Array(values))
+			},
+			ResolvedTypes::Void => Ok(RValue::Void),
+			_ => Err(CodegenError(src, format!("unable to unpack {:?} as type {:?}", packed, typ))),
+		}
+	}
+	// Convert a value so it is compatible with a type; including list initialisers for structures
+	pub fn convert(&mut self, value: &RValue, typ: &ResolvedType, src: SrcInfo) -> Result<RValue, CodegenError> {
+		match (&typ.typ, value) {
+			(ResolvedTypes::Integer(it), v) if v.is_scalar() => Ok(self.convert_scalar(v, *it, src)),
+			(ResolvedTypes::Array(base, count), RValue::Array(vals)) => {
+				if vals.len() > *count {
+					return Err(CodegenError(src, format!("too many values ({}) for array of length {}", vals.len(), count)));
+				}
+				let mut result = Vec::new();
+				for i in 0..*count {
+					result.push(match vals.get(i) {
+						Some(v) => self.convert(v, base, src)?,
+						None => RValue::from_type(self, &base.typ),
+					});
+				}
+				Ok(RValue::Array(result))
+			},
+			(ResolvedTypes::Struct(key), RValue::Structure(sv)) if &sv.typ == key => Ok(value.clone()),
+			(ResolvedTypes::Struct(key), RValue::Union(uv)) if &uv.typ == key => Ok(value.clone()),
+			(ResolvedTypes::Struct(key), RValue::Array(vals)) => {
+				// list initialiser; in member order
+				let (members, is_union) = {
+					let data = self.structs.get(key).unwrap();
+					(data.members.clone(), data.is_union)
+				};
+				if vals.len() > members.len() || (is_union && vals.len() > 1) {
+					return Err(CodegenError(src, format!("too many values ({}) in initialiser for {:?}", vals.len(), key)));
+				}
+				let mut result = RValue::from_type(self, &typ.typ);
+				for (i, v) in vals.iter().enumerate() {
+					result = self.assign_path(members[i].0, result, typ, &[ValuePathItem::Member(members[i].0)], v, None, src)?;
+				}
+				Ok(result)
+			},
+			(ResolvedTypes::Void, _) | (ResolvedTypes::AutoInt, _) => Ok(value.clone()),
+			_ => Err(CodegenError(src, format!("unable to convert {:?} to type {:?}", value, typ))),
+		}
+	}
+	// Get a member of a structure or union value
+	pub fn get_member(&mut self, value: &RValue, member: IdString, src: SrcInfo) -> Result<RValue, CodegenError> {
+		match value {
+			RValue::Structure(sv) => sv.values.get(&member).cloned()
+				.ok_or_else(|| CodegenError(src, format!("structure {:?} has no member named {:?}", sv.typ, member))),
+			RValue::Union(uv) => {
+				let member_type = self.structs.get(&uv.typ).unwrap().member(member).cloned()
+					.ok_or_else(|| CodegenError(src, format!("union {:?} has no member named {:?}", uv.typ, member)))?;
+				let width = member_type.pack(self)?.width;
+				let bits = self.slice_get(&uv.bits, 0, OperandType::unsigned(width), src);
+				self.unpack_value(&bits, &member_type, src)
+			},
+			_ => Err(CodegenError(src, format!("attempting to access member {:?} of non-structure value {:?}", member, value))),
+		}
+	}
+	// Update part of a value, following a path, returning the new value
+	// Conditionals from cond_idx onwards are applied at the leaves; so unchanged parts of the value don't get Cond primitives
+	pub fn assign_path(&mut self, base_name: IdString, curr_value: RValue, typ: &ResolvedType, path: &[ValuePathItem], new_value: &RValue, cond_idx: Option<usize>, src: SrcInfo) -> Result<RValue, CodegenError> {
+		if path.is_empty() {
+			return match (&typ.typ, curr_value) {
+				(ResolvedTypes::Array(base, count), RValue::Array(mut vals)) => {
+					let new_vals = match self.convert(new_value, typ, src)? { RValue::Array(v) => v, _ => unreachable!() };
+					for i in 0..*count {
+						let old = std::mem::replace(&mut vals[i], RValue::Void);
+						vals[i] = self.assign_path(base_name, old, base, &[], &new_vals[i], cond_idx, src)?;
 					}
+					Ok(RValue::Array(vals))
+				},
+				(ResolvedTypes::Struct(_), RValue::Structure(mut sv)) => {
+					let new_sv = match self.convert(new_value, typ, src)? { RValue::Structure(v) => v, _ => unreachable!() };
+					let members = self.structs.get(&sv.typ).unwrap().members.clone();
+					for (name, mt) in members.iter() {
+						let old = sv.values.remove(name).unwrap();
+						let updated = self.assign_path(base_name, old, mt, &[], &new_sv.values[name], cond_idx, src)?;
+						sv.values.insert(*name, updated);
+					}
+					Ok(RValue::Structure(sv))
+				},
+				(ResolvedTypes::Struct(_), RValue::Union(mut uv)) => {
+					let new_uv = match self.convert(new_value, typ, src)? { RValue::Union(v) => v, _ => unreachable!() };
+					let bits_type = ResolvedType { typ: ResolvedTypes::Integer(self.scalar_type(&uv.bits).unwrap()), is_const: false, is_static: false };
+					uv.bits = Box::new(self.assign_path(base_name, *uv.bits, &bits_type, &[], &new_uv.bits, cond_idx, src)?);
+					Ok(RValue::Union(uv))
 				},
-				RValue::Structure(sv) => {
-					for (key, val) in sv.values.iter() {
-						let mut next_path = Vec::from(path);
-						next_path.push(ValuePathItem::Member(*key));
-						self.assign_variable(var, &next_path, val, src);
+				(_, old) => {
+					// at the end of the line, actually assign the value
+					let converted = self.convert(new_value, typ, src)?;
+					match cond_idx {
+						Some(cond_idx) if old.is_scalar() && converted.is_scalar() => Ok(self.apply_conditionals(base_name, cond_idx, old, converted, src)),
+						_ => Ok(converted),
 					}
+				},
+			};
+		}
+		match (&path[0], &typ.typ, curr_value) {
+			(ValuePathItem::ConstIndex(idx), ResolvedTypes::Array(base, count), RValue::Array(mut vals)) => {
+				if *idx >= *count {
+					return Err(CodegenError(src, format!("index {} out of range for array of length {}", idx, count)));
 				}
-				_ => unreachable!(),
-			}
+				let old = std::mem::replace(&mut vals[*idx], RValue::Void);
+				vals[*idx] = self.assign_path(base_name, old, base, &path[1..], new_value, cond_idx, src)?;
+				Ok(RValue::Array(vals))
+			},
+			(ValuePathItem::Member(m), ResolvedTypes::Struct(key), RValue::Structure(mut sv)) => {
+				let member_type = self.structs.get(key).unwrap().member(*m).cloned()
+					.ok_or_else(|| CodegenError(src, format!("structure {:?} has no member named {:?}", key, m)))?;
+				let old = sv.values.remove(m).unwrap();
+				let updated = self.assign_path(base_name, old, &member_type, &path[1..], new_value, cond_idx, src)?;
+				sv.values.insert(*m, updated);
+				Ok(RValue::Structure(sv))
+			},
+			(ValuePathItem::Member(m), ResolvedTypes::Struct(key), RValue::Union(mut uv)) => {
+				// unpack the member from the shared bits, update it, and then write it back
+				let member_type = self.structs.get(key).unwrap().member(*m).cloned()
+					.ok_or_else(|| CodegenError(src, format!("union {:?} has no member named {:?}", key, m)))?;
+				let old = self.get_member(&RValue::Union(uv.clone()), *m, src)?;
+				let updated = self.assign_path(base_name, old, &member_type, &path[1..], new_value, cond_idx, src)?;
+				let packed = self.pack_value(&updated, &member_type, src)?;
+				uv.bits = Box::new(self.slice_set(&uv.bits, 0, &packed, src));
+				Ok(RValue::Union(uv))
+			},
+			(p, _, v) => Err(CodegenError(src, format!("unable to assign to {:?} of {:?}", p, v))),
 		}
 	}
+	pub fn assign_variable(&mut self, var: StoreIndex<Variable>, path: &[ValuePathItem], new_value: &RValue, src: SrcInfo) -> Result<(), CodegenError> {
+		let (value_name, typ, cond_idx) = {
+			let v = self.vars.get(var);
+			(v.name, v.typ.clone(), v.cond_idx)
+		};
+		let curr_value = self.vars.get(var).value.clone();
+		let new_value = self.assign_path(value_name, curr_value, &typ, path, new_value, Some(cond_idx), src)?;
+		self.vars.get_mut(var).value = new_value;
+		Ok(())
+	}
 	pub fn scope(&mut self) -> &mut GenScope {
 		self.scopes.last_mut().unwrap()
 	}
 	pub fn push_scope(&mut self) {
-		self.scopes.push(GenScope::new(self.conds.len()));
+		self.scope_idx += 1;
+		self.scopes.push(GenScope::new(IdentPart::Anon(self.scope_idx), self.conds.len()));
 	}
 	pub fn pop_scope(&mut self) {
 		self.scopes.pop();
@@ -117,39 +449,133 @@ impl <'a> GenState<'a> {
 	pub fn pop_cond(&mut self) {
 		self.conds.pop();
 	}
-	pub fn lookup_var(&self, ident: IdString) -> NullableIndex<Variable> {
-		for scope in self.scopes.iter().rev() {
-			if let Some(var) = scope.var_map.get(&ident) {
-				return NullableIndex::some(*var);
+	// Index into the condition stack where the current scope starts
+	pub fn scope_cond_idx(&self) -> usize {
+		self.scopes.last().unwrap().cond_idx
+	}
+	pub fn cond_stack(&self) -> &[(StoreIndex<Node>, bool)] {
+		&self.conds
+	}
+	// Prefix a name with the current namespace
+	pub fn qualify(&mut self, name: IdString) -> IdString {
+		let mut qual_name = String::new();
+		for ns in self.ns_path.iter() {
+			qual_name.push_str(self.ids.get_str(*ns));
+			qual_name.push_str("::");
+		}
+		qual_name.push_str(self.ids.get_str(name));
+		self.ids.id(&qual_name)
+	}
+	// The name to register a declaration under; only namespace level declarations are qualified
+	pub fn decl_name(&mut self, name: IdString) -> IdString {
+		if self.scopes.len() == 1 { self.qualify(name) } else { name }
+	}
+	// Search local scopes innermost first, then the outermost scope for the name qualified by each enclosing namespace
+	fn lookup<'b, T>(&'b self, ident: IdString, get: impl Fn(&'b GenScope, IdString) -> Option<T>) -> Option<T> {
+		for scope in self.scopes[self.frame..].iter().rev() {
+			if let Some(result) = get(scope, ident) {
+				return Some(result);
+			}
+		}
+		for i in (0..=self.ns_path.len()).rev() {
+			let mut qual_name = String::new();
+			for ns in self.ns_path[0..i].iter() {
+				qual_name.push_str(self.ids.get_str(*ns));
+				qual_name.push_str("::");
+			}
+			qual_name.push_str(self.ids.get_str(ident));
+			if let Some(result) = self.ids.get_id(&qual_name).and_then(|id| get(&self.scopes[0], id)) {
+				return Some(result);
 			}
 		}
-		return NullableIndex::none();
+		None
+	}
+	pub fn lookup_var(&self, ident: IdString) -> NullableIndex<Variable> {
+		match self.lookup(ident, |s, id| s.var_map.get(&id).copied()) {
+			Some(var) => NullableIndex::some(var),
+			None => NullableIndex::none(),
+		}
 	}
 	pub fn lookup_type(&self, ident: IdString) -> Option<&ResolvedType> {
-		for scope in self.scopes.iter().rev() {
-			if let Some(typ) = scope.type_map.get(&ident) {
-				return Some(typ);
-			}
+		self.lookup(ident, |s, id| s.type_map.get(&id))
+	}
+	pub fn lookup_struct(&self, ident: IdString) -> Option<&StructDecl> {
+		self.lookup(ident, |s, id| s.struct_map.get(&id))
+	}
+	pub fn lookup_func(&self, ident: IdString) -> Option<&Vec<FuncDecl>> {
+		self.lookup(ident, |s, id| s.func_map.get(&id))
+	}
+	pub fn scope_depth(&self) -> usize {
+		self.scopes.len()
+	}
+	// The unique identifier for something declared with a given name in the current scope
+	pub fn ident(&self, name: IdString) -> Identifier {
+		let mut parts = SSOArray::from_slice(&self.scopes.iter().map(|s| s.name).collect::<Vec<_>>());
+		parts.push(IdentPart::Str(name));
+		Identifier { parts: parts }
+	}
+}
+
+// Namespace level declarations; elaborated once and then shared by every block
+pub struct Globals {
+	pub vars: ObjectStore<Variable>,
+	pub structs: FxHashMap<ResolvedKey, DerivedStruct>,
+	pub scope: GenScope,
+}
+
+impl Globals {
+	pub fn new() -> Globals {
+		Globals {
+			vars: ObjectStore::new(),
+			structs: FxHashMap::default(),
+			scope: GenScope::new(IdentPart::Anon(0), 0),
 		}
-		return None;
 	}
 }
 
+// A structure declaration, the namespace it was declared in, and any specialisations of it
+#[derive(Clone)]
+pub struct StructDecl {
+	pub ident: Identifier,
+	pub ns_path: Vec<IdString>,
+	pub def: StructureDef,
+	pub specs: Vec<StructureDef>,
+}
+
+// A function declaration, the namespace it was declared in, and the template arguments it is elaborated with; which
+// are those of the structure for member functions
+#[derive(Clone)]
+pub struct FuncDecl {
+	pub ns_path: Vec<IdString>,
+	pub func: Function,
+	pub templ: Vec<(IdString, ResolvedArg)>,
+}
+
 // Codegen state for a specific scope
+#[derive(Clone)]
 pub struct GenScope {
+	// Name of the scope, used to create unique identifiers
+	pub name: IdentPart,
 	// Mapping from var names in the current scope to concrete variable indices
 	pub var_map: FxHashMap<IdString, StoreIndex<Variable>>,
 	// Mapping from type names in the current scope to resolved types
 	pub type_map: FxHashMap<IdString, ResolvedType>,
+	// Mapping from structure names in the current scope to their definitions
+	pub struct_map: FxHashMap<IdString, StructDecl>,
+	// Mapping from function names in the current scope to their overloads
+	pub func_map: FxHashMap<IdString, Vec<FuncDecl>>,
 	// Index into the condition stack where this scope starts
 	pub cond_idx: usize,
 }
 
 impl GenScope {
-	pub fn new(cond_idx: usize) -> GenScope {
+	pub fn new(name: IdentPart, cond_idx: usize) -> GenScope {
 		GenScope {
+			name: name,
 			var_map: FxHashMap::default(),
 			type_map: FxHashMap::default(),
+			struct_map: FxHashMap::default(),
+			func_map: FxHashMap::default(),
 			cond_idx: cond_idx,
 		}
 	}
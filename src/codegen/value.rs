@@ -1,15 +1,20 @@
 use std::fmt;
 use crate::codegen::{GenState, ResolvedType, ResolvedTypes, ResolvedKey};
-use crate::ast::{Function};
+use crate::ast::{Function, SrcInfo};
 use crate::core::{BitVector, StoreIndex, IdString};
 use crate::design::Node;
 use rustc_hash::FxHashMap;
 
 // All variables are tracked this way
+#[derive(Clone)]
 pub struct Variable {
 	pub name: IdString,
 	pub typ: ResolvedType,
 	pub value: RValue,
+	// Where the variable was declared, for diagnostics
+	pub src: SrcInfo,
+	// Index into the condition stack where the declaring scope starts; only later conditions apply to assignments
+	pub cond_idx: usize,
 }
 
 // The contents of a structure
@@ -19,6 +24,13 @@ pub struct StructureValue {
 	pub values: FxHashMap<IdString, RValue>,
 }
 
+// The contents of a packed union; stored as the shared bits with members sliced out on access
+#[derive(Clone, Eq, PartialEq)]
+pub struct UnionValue {
+	pub typ: ResolvedKey,
+	pub bits: Box<RValue>,
+}
+
 // Lots of different things can be 'values' in our codegen IL
 #[derive(Clone, Eq, PartialEq)]
 pub enum RValue {
@@ -26,6 +38,7 @@ pub enum RValue {
 	Constant(BitVector), // a resolved constant value
 	Node(StoreIndex<Node>), // variables become pointers to nodes in the design being elaborated
 	Structure(StructureValue), // a structure, stored as the structure type and name-value map
+	Union(UnionValue), // a union, stored as the union type and the packed value of all members
 	Array(Vec<RValue>), // an array, stored as a list of values
 	Func(StoreIndex<Function>), // a function 'pointer'
 }
@@ -47,10 +60,21 @@ impl RValue {
 		match self {
 			RValue::Constant(_) | RValue::Func(_) => true,
 			RValue::Structure(sv) => sv.values.values().all(|v| v.is_fully_const()),
+			RValue::Union(uv) => uv.bits.is_fully_const(),
 			RValue::Array(vals) => vals.iter().all(|v| v.is_fully_const()),
 			_ => false,
 		}
 	}
+	// Whether any bits of a constant leaf are undefined
+	pub fn has_undef(&self) -> bool {
+		match self {
+			RValue::Constant(c) => !c.is_defined(),
+			RValue::Structure(sv) => sv.values.values().any(|v| v.has_undef()),
+			RValue::Union(uv) => uv.bits.has_undef(),
+			RValue::Array(vals) => vals.iter().any(|v| v.has_undef()),
+			_ => false,
+		}
+	}
 	// Create an outline value from a resolved type (with leaf values filled with Void)
 	pub fn from_type(st: &GenState, ty: &ResolvedTypes) -> RValue {
 		use RValue::*;
@@ -58,10 +82,19 @@ impl RValue {
 			ResolvedTypes::Integer(it) => Constant(BitVector::undefined(it.width, it.is_signed)),
 			ResolvedTypes::Struct(key) => {
 				let struct_data = st.structs.get(key).unwrap();
-				Structure(StructureValue {
-					typ: key.clone(),
-					values: struct_data.members.iter().map(|(k, t)| (*k, Self::from_type(st, &t.typ))).collect(),
-				})
+				if struct_data.is_union {
+					// union members are checked to be packable when the union is derived
+					let width = ResolvedType { typ: ty.clone(), is_const: false, is_static: false }.pack(st).unwrap().width;
+					Union(UnionValue {
+						typ: key.clone(),
+						bits: Box::new(Constant(BitVector::undefined(width, false))),
+					})
+				} else {
+					Structure(StructureValue {
+						typ: key.clone(),
+						values: struct_data.members.iter().map(|(k, t)| (*k, Self::from_type(st, &t.typ))).collect(),
+					})
+				}
 			}
 			ResolvedTypes::Array(base, count) => Array((0..*count).map(|_| Self::from_type(st, &base.typ)).collect()),
 			ResolvedTypes::Reference(_) => unimplemented!(), // special_case
@@ -78,6 +111,7 @@ impl RValue {
 				RValue::Constant(bv) => Some(ResolvedTypes::Integer(bv.op_type())),
 				RValue::Node(n) => Some(ResolvedTypes::Integer(st.des.nodes.get(*n).typ)),
 				RValue::Structure(sv) => Some(ResolvedTypes::Struct(sv.typ.clone())),	
+				RValue::Union(uv) => Some(ResolvedTypes::Struct(uv.typ.clone())),
 				RValue::Array(vals) => {
 					let mut typ = ResolvedType { is_const: true, is_static: false, typ: ResolvedTypes::Void };
 					for val in vals.iter() {
@@ -146,7 +180,18 @@ impl fmt::Debug for RValue {
 			Void => write!(fmt, "<void>")?,
 			Constant(v) => write!(fmt, "{:?}", v)?,
 			Node(n) => write!(fmt, "{:?}", n)?,
-			_ => unimplemented!(),
+			Structure(sv) => {
+				write!(fmt, "{:?} {{", sv.typ)?;
+				for (k, v) in sv.values.iter() { write!(fmt, "{:?}: {:?}, ", k, v)?; }
+				write!(fmt, "}}")?;
+			},
+			Union(uv) => write!(fmt, "{:?} {{{:?}}}", uv.typ, uv.bits)?,
+			Array(vals) => {
+				write!(fmt, "{{")?;
+				for v in vals.iter() { write!(fmt, "{:?}, ", v)?; }
+				write!(fmt, "}}")?;
+			},
+			Func(f) => write!(fmt, "{:?}", f)?,
 		}
 		Ok(())
 	}
@@ -235,6 +235,30 @@ impl BitVector {
 	pub fn iter(&self) -> impl Iterator<Item = State> + '_ {
 		(0..self.len()).map(move |i| self.get(i).unwrap())
 	}
+	// Extract 'width' bits starting at 'offset'; bits off the end are zero
+	pub fn slice(&self, offset: usize, width: usize) -> BitVector {
+		let mut result = BitVector::new(width, false);
+		for i in 0..width {
+			result.set(i, self.get(offset + i).unwrap_or(State::S0));
+		}
+		result
+	}
+	// Replace the bits starting at 'offset' with 'value'
+	pub fn set_slice(&mut self, offset: usize, value: &BitVector) {
+		for (i, b) in value.iter().enumerate() {
+			if offset + i < self.length {
+				self.set(offset + i, b);
+			}
+		}
+	}
+	// Truncate or extend (sign extending if signed) to a new width
+	pub fn resize(&self, width: usize, is_signed: bool) -> BitVector {
+		let mut result = BitVector::new(width, is_signed);
+		for i in 0..width {
+			result.set(i, self.get_ext(i));
+		}
+		result
+	}
 }
 
 impl fmt::Display for BitVector {
@@ -359,6 +383,18 @@ mod test {
 		assert_eq!(v.to_string(), "11zxzx10");
 	}
 
+	#[test]
+	fn slices() {
+		let mut v = BitVector::from_u64(0xA5, 8);
+		assert_eq!(v.slice(4, 4), BitVector::from_u64(0xA, 4));
+		assert_eq!(v.slice(6, 4), BitVector::from_u64(0x2, 4));
+		v.set_slice(0, &BitVector::from_u64(0x3, 4));
+		assert_eq!(v, BitVector::from_u64(0xA3, 8));
+		assert_eq!(BitVector::from_i64(-2, 4).resize(8, true), BitVector::from_i64(0xFE, 8));
+		assert_eq!(BitVector::from_u64(0xE, 4).resize(8, false), BitVector::from_u64(0xE, 8));
+		assert_eq!(BitVector::from_u64(0x1E, 8).resize(4, false), BitVector::from_u64(0xE, 4));
+	}
+
 	#[test]
 	fn from_yosys_str() {
 		use State::*;
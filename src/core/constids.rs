@@ -26,6 +26,10 @@ constids! {
 	r#else,
 	r#for,
 	r#while,
+	r#do,
+	r#switch,
+	r#case,
+	default,
 	r#const,
 	r#static,
 	multicycle,
@@ -35,6 +39,9 @@ constids! {
 	r#return,
 	sizeof,
 	widthof,
+	cat,
+	rep,
+	range,
 	r#typeof,
 	block,
 	clock,
@@ -49,6 +56,14 @@ constids! {
 	input,
 	output,
 	static_cast,
+	static_assert,
+	assert,
+	assume,
+	include,
+	import,
+	// Attributes
+	doc,
+	unroll,
 	// Primitive ports
 	A,
 	B,
@@ -57,4 +72,5 @@ constids! {
 	SEL,
 	Q,
 	PORT,
+	EN,
 }
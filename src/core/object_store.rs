@@ -76,11 +76,13 @@ impl <T> NullValue for StoreIndex<T> {
 
 pub type NullableIndex<T> = Nullable<StoreIndex<T>>;
 
+#[derive(Clone)]
 pub struct Slot<T> {
 	data: Option<T>,
 	next_free: u32,
 }
 
+#[derive(Clone)]
 pub struct ObjectStore<T> {
 	slots: Vec<Slot<T>>,
 	next_free: u32,
@@ -1,4 +1,5 @@
 use crate::core::{State, BitVector};
+use std::cmp::Ordering;
 use std::fmt;
 
 // Operand type
@@ -85,7 +86,7 @@ impl BasicOp {
 			Mod => OperandType::new(t[1].width + OperandType::extra_bit(t[0], t[1]), t[0].is_signed | t[1].is_signed),
 			Neg => OperandType::signed(t[0].width + 1),
 			Eq | Neq | Gt | Lt | GtEq | LtEq => OperandType::BOOL,
-			Shl | Shr => unimplemented!(), // icky case
+			Shl | Shr => t[0], // shifts never change the width, like C
 			BwAnd | BwOr | BwXor => OperandType::merge(t[0], t[1]),
 			BwNot => t[0],
 			LogAnd | LogOr | LogNot | LogCast => OperandType::BOOL,
@@ -139,10 +140,91 @@ impl BasicOp {
 			LogCast => {
 				result.set(0, if operands[0].iter().any(|b| b == State::S1) { State::S1 } else { State::S0 });
 			},
-			_ => unimplemented!()
+			Neg => result = Sub.apply(&[BitVector::from_u64(0, 1), operands[0].clone()]).resize(result_type.width, true),
+			Eq | Neq | Gt | Lt | GtEq | LtEq => {
+				let cmp = Self::compare(&operands[0], &operands[1]);
+				let value = match cmp {
+					None => State::Sx,
+					Some(ord) => {
+						let is_true = match &self {
+							Eq => ord == Ordering::Equal,
+							Neq => ord != Ordering::Equal,
+							Gt => ord == Ordering::Greater,
+							Lt => ord == Ordering::Less,
+							GtEq => ord != Ordering::Less,
+							_ => ord != Ordering::Greater,
+						};
+						if is_true { State::S1 } else { State::S0 }
+					}
+				};
+				result.set(0, value);
+			},
+			Shl | Shr => {
+				match operands[1].as_def_u64() {
+					Some(shift) => {
+						let shift = shift as usize;
+						for i in 0..result.len() {
+							result.set(i, if *self == Shl {
+								if i >= shift { operands[0].get_ext(i - shift) } else { State::S0 }
+							} else {
+								operands[0].get_ext(i + shift)
+							});
+						}
+					},
+					None => result = BitVector::undefined(result_type.width, result_type.is_signed),
+				}
+			},
+			Div | Mod => {
+				// Long division of the magnitudes, at the full operand width. Like C, the quotient rounds
+				// towards zero and the remainder takes the sign of the dividend.
+				let width = std::cmp::max(operands[0].len(), operands[1].len()) + 1;
+				let is_neg = |v: &BitVector| v.get_ext(width - 1) == State::S1;
+				let magnitude = |v: &BitVector| if is_neg(v) { Neg.apply(std::slice::from_ref(v)) } else { v.clone() }.resize(width, false);
+				let (a, b) = (magnitude(&operands[0]), magnitude(&operands[1]));
+				if a.has_undef() || b.has_undef() || b.iter().all(|x| x == State::S0) {
+					return BitVector::undefined(result_type.width, result_type.is_signed);
+				}
+				let mut quot = BitVector::new(width, false);
+				let mut rem = BitVector::new(width, false);
+				for i in (0..width).rev() {
+					rem = Shl.apply(&[rem, BitVector::from_u64(1, 1)]);
+					rem.set(0, a.get_ext(i));
+					if Self::compare(&rem, &b) != Some(Ordering::Less) {
+						rem = Sub.apply(&[rem, b.clone()]).resize(width, false);
+						quot.set(i, State::S1);
+					}
+				}
+				let (value, negate) = if *self == Div {
+					(quot, is_neg(&operands[0]) != is_neg(&operands[1]))
+				} else {
+					(rem, is_neg(&operands[0]))
+				};
+				result = if negate { Neg.apply(&[value]) } else { value }.resize(result_type.width, result_type.is_signed);
+			},
 		}
 		result
 	}
+	// Compare two bitvectors numerically, respecting signedness; or None if any bits are undefined
+	pub fn compare(a: &BitVector, b: &BitVector) -> Option<Ordering> {
+		if a.has_undef() || b.has_undef() {
+			return None;
+		}
+		// Extend both to a common width, one more than the largest so sign bits are correct
+		let width = std::cmp::max(a.len(), b.len()) + 1;
+		let sign_a = a.get_ext(width - 1);
+		let sign_b = b.get_ext(width - 1);
+		if sign_a != sign_b {
+			return Some(if sign_a == State::S1 { Ordering::Less } else { Ordering::Greater });
+		}
+		for i in (0..(width - 1)).rev() {
+			match (a.get_ext(i), b.get_ext(i)) {
+				(State::S1, State::S0) => return Some(Ordering::Greater),
+				(State::S0, State::S1) => return Some(Ordering::Less),
+				_ => {},
+			}
+		}
+		Some(Ordering::Equal)
+	}
 }
 
 #[cfg(test)]
@@ -161,6 +243,33 @@ mod test {
 		assert_eq!(BasicOp::BwNot.apply(&[BitVector::from_u64(0b11000110, 8)]), BitVector::from_u64(0b00111001, 8));
 	}
 	#[test]
+	fn compare() {
+		assert_eq!(BasicOp::Lt.apply(&[BitVector::from_u64(3, 8), BitVector::from_u64(200, 8)]), BitVector::from_u64(1, 1));
+		assert_eq!(BasicOp::Lt.apply(&[BitVector::from_i64(-3, 8), BitVector::from_u64(2, 2)]), BitVector::from_u64(1, 1));
+		assert_eq!(BasicOp::GtEq.apply(&[BitVector::from_u64(0xFD, 8), BitVector::from_i64(-3, 8)]), BitVector::from_u64(1, 1));
+		assert_eq!(BasicOp::Eq.apply(&[BitVector::from_u64(5, 3), BitVector::from_u64(5, 64)]), BitVector::from_u64(1, 1));
+		assert_eq!(BasicOp::Neq.apply(&[BitVector::from_u64(5, 3), BitVector::from_u64(5, 64)]), BitVector::from_u64(0, 1));
+		assert_eq!(BasicOp::Eq.apply(&[BitVector::undefined(3, false), BitVector::from_u64(5, 64)]), BitVector::from_bits(&[State::Sx]));
+	}
+	#[test]
+	fn shift() {
+		assert_eq!(BasicOp::Shl.apply(&[BitVector::from_u64(0b0110, 4), BitVector::from_u64(1, 2)]), BitVector::from_u64(0b1100, 4));
+		assert_eq!(BasicOp::Shr.apply(&[BitVector::from_u64(0b0110, 4), BitVector::from_u64(2, 2)]), BitVector::from_u64(0b0001, 4));
+	}
+	#[test]
+	fn div() {
+		assert_eq!(BasicOp::Div.apply(&[BitVector::from_u64(100, 8), BitVector::from_u64(7, 8)]), BitVector::from_u64(14, 8));
+		assert_eq!(BasicOp::Mod.apply(&[BitVector::from_u64(100, 8), BitVector::from_u64(7, 8)]), BitVector::from_u64(2, 8));
+		assert_eq!(BasicOp::Div.apply(&[BitVector::from_i64(-100, 8), BitVector::from_u64(7, 8)]), BitVector::from_i64(-14, 9).resize(9, true));
+		assert_eq!(BasicOp::Mod.apply(&[BitVector::from_i64(-100, 8), BitVector::from_u64(7, 8)]), BitVector::from_i64(-2, 9).resize(9, true));
+		assert_eq!(BasicOp::Div.apply(&[BitVector::from_u64(7, 8), BitVector::from_u64(0, 8)]), BitVector::undefined(8, false));
+		// Operands wider than 64 bits
+		let wide = BitVector::from_u64(3, 2).resize(100, false);
+		let wide = BasicOp::Shl.apply(&[wide, BitVector::from_u64(80, 7)]);
+		assert_eq!(BasicOp::Div.apply(&[wide.clone(), BitVector::from_u64(1 << 16, 32)]), BasicOp::Shl.apply(&[BitVector::from_u64(3, 100), BitVector::from_u64(64, 7)]));
+		assert_eq!(BasicOp::Mod.apply(&[BasicOp::Add.apply(&[wide, BitVector::from_u64(5, 8)]).resize(100, false), BitVector::from_u64(1 << 16, 32)]), BitVector::from_u64(5, 32));
+	}
+	#[test]
 	fn logical() {
 		assert_eq!(BasicOp::LogCast.apply(&[BitVector::from_u64(0b0000, 4)]), BitVector::from_u64(0b0, 1));
 		assert_eq!(BasicOp::LogCast.apply(&[BitVector::from_u64(0b1010, 4)]), BitVector::from_u64(0b1, 1));
@@ -11,6 +11,8 @@ pub struct Context {
 
 pub struct Design {
 	pub name: IdString,
+	// Documentation of the block the design was elaborated from
+	pub doc: Option<String>,
 	pub nodes: NamedStore<Node>,
 	pub prims: NamedStore<Primitive>,
 	pub top_ports: Vec<StoreIndex<Primitive>>,
@@ -21,6 +23,7 @@ impl Design {
 	pub fn new(name: IdString) -> Design {
 		Design {
 			name: name,
+			doc: None,
 			nodes: NamedStore::new(),
 			prims: NamedStore::new(),
 			top_ports: Vec::new(),
@@ -40,17 +43,23 @@ impl Design {
 		self.prims.get_mut(driver).ports.add(PrimitivePort::output(driver_port, node_idx))?;
 		Ok(node_idx)
 	}
-	pub fn add_port(&mut self, name: IdString, ty: OperandType, dir: PortDir) -> Result<StoreIndex<Node>, String> {
-		let prim_idx = self.add_prim(name, PrimitiveType::TopPort, SrcInfo::default())?;
+	pub fn add_port(&mut self, name: IdString, ty: OperandType, dir: PortDir, src: SrcInfo) -> Result<StoreIndex<Node>, String> {
+		let prim_idx = self.add_prim(name, PrimitiveType::TopPort, src)?;
 		match dir {
 			PortDir::Input => {
-				self.add_node(name, ty, SrcInfo::default(), prim_idx, constids::PORT)
+				self.add_node(name, ty, src, prim_idx, constids::PORT)
 			},
 			PortDir::Output => {
 				unimplemented!();
 			}
 		}
 	}
+	// An output port of the design, driven by an existing node
+	pub fn add_output(&mut self, name: IdString, node: StoreIndex<Node>, src: SrcInfo) -> Result<StoreIndex<Primitive>, String> {
+		let prim_idx = self.add_prim(name, PrimitiveType::TopPort, src)?;
+		self.add_prim_input(prim_idx, constids::PORT, node)?;
+		Ok(prim_idx)
+	}
 	pub fn add_prim_input(&mut self, prim: StoreIndex<Primitive>, name: IdString, node: StoreIndex<Node>) -> Result<StoreIndex<PrimitivePort>, String> {
 		let usr_idx = self.nodes.get_mut(node).users.add(PortRef { prim: prim, port: name });
 		self.prims.get_mut(prim).ports.add(PrimitivePort::input(name, node, usr_idx))
@@ -99,7 +108,7 @@ impl Design {
 		dead_nodes.len()
 	}
 	pub fn trim_prims(&mut self) -> usize {
-		let dead_prims : Vec<StoreIndex<Primitive>> = self.prims.iter().filter_map(|(i, p)| if !p.ports.iter().any(|(_, port)| port.is_output()) { Some(i) } else { None }).collect();
+		let dead_prims : Vec<StoreIndex<Primitive>> = self.prims.iter().filter_map(|(i, p)| if !p.typ.is_property() && p.typ != PrimitiveType::TopPort && !p.ports.iter().any(|(_, port)| port.is_output()) { Some(i) } else { None }).collect();
 		for p in dead_prims.iter() {
 			self.remove_prim(*p);
 		}
@@ -119,6 +128,9 @@ impl Design {
 
 impl fmt::Debug for Design {
 	fn fmt(&self, fmt : &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+		for line in self.doc.iter().flat_map(|d| d.lines()) {
+			writeln!(fmt, "// {}", line)?;
+		}
 		writeln!(fmt, "design {:?}", self.name)?;
 		for (_, node) in self.nodes.iter() {
 			writeln!(fmt, "    node {:?} {:?}", node.typ, node.name)?;
@@ -129,6 +141,9 @@ impl fmt::Debug for Design {
 			for (_, port) in prim.ports.iter() {
 				write!(fmt, " .{:?}({:?})", port.name, port.node.map(|n| self.nodes.get(n).name).unwrap_or(IdString::NONE))?;
 			}
+			for (name, value) in prim.attrs.iter() {
+				write!(fmt, " [[{:?}={:?}]]", name, value)?;
+			}
 			writeln!(fmt, "")?;
 		}
 		writeln!(fmt, "")?;
@@ -163,4 +178,18 @@ mod test {
 		assert_eq!(des.prims.count(), 0);
 		Ok(())
 	}
+	#[test]
+	fn trim_keeps_properties() -> Result<(), String> {
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let mut des = Design::new(ids.id("top"));
+		des.add_const(&mut ids, BitVector::from_u64(0, 1), SrcInfo::default());
+		let cond = des.add_const(&mut ids, BitVector::from_u64(1, 1), SrcInfo::default());
+		let prop = des.add_prim(ids.id("check"), PrimitiveType::Assert, SrcInfo::default())?;
+		des.add_prim_input(prop, constids::A, cond)?;
+		assert_eq!(des.trim(), 2);
+		assert_eq!(des.prims.count(), 2);
+		assert_eq!(des.nodes.count(), 1);
+		Ok(())
+	}
 }
\ No newline at end of file
@@ -12,6 +12,8 @@ pub enum SpecialOperation {
 	SliceGetVar{step: usize, width: usize}, // run time bitslice extraction
 	SliceSetFix{offset: usize, width: usize}, // compile time bitslice replacement
 	SliceSetVar{step: usize, width: usize}, // run time bitslice replacement
+	Resize(usize), // truncation or extension to a given width; sign extending if the input is signed
+	Cat, // concatenation, with A in the least significant bits and B above it
 }
 
 // The various kinds of registers we use
@@ -42,6 +44,15 @@ pub enum PrimitiveType {
 	Reg(Register),
 	Mem(Memory),
 	TopPort,
+	Assert, // property that A holds whenever EN is set; has no outputs but is never trimmed
+	Assume, // as Assert, but constraining the inputs for formal verification
+}
+
+impl PrimitiveType {
+	// Primitives that are kept even though nothing uses their outputs
+	pub fn is_property(&self) -> bool {
+		matches!(self, PrimitiveType::Assert | PrimitiveType::Assume)
+	}
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -11,6 +11,7 @@ pub mod backend;
 
 use std::io::Read;
 use std::fs::File;
+use std::path::PathBuf;
 use crate::core::*;
 use crate::parser::*;
 use crate::ast::*;
@@ -43,37 +44,113 @@ fn conv_ids(ids: &IdStringDb, s: &str) -> String {
 	result
 }
 
+// Prefix a message with where in the source it relates to, if known
+fn located(ids: &IdStringDb, src: SrcInfo, msg: &str) -> String {
+	if src.file == IdString::NONE {
+		conv_ids(ids, msg)
+	} else {
+		format!("{}:{}:{}: {}", ids.get_str(src.file), src.start.line, src.start.col, conv_ids(ids, msg))
+	}
+}
+
+// Elaborate all the blocks in a namespace and its children
+fn elab_namespace(ids: &mut IdStringDb, globals: &Globals, ns: &Namespace, path: &mut Vec<IdString>) -> Result<(), String> {
+	for item in ns.content.iter() {
+		match item {
+			NamespaceItem::Namespace(inner) => {
+				path.push(inner.name.unwrap());
+				elab_namespace(ids, globals, inner, path)?;
+				path.pop();
+			},
+			NamespaceItem::Stmt(st) => if let StatementType::Module(m) = &st.ty {
+				// specialisations are elaborated in place of the block they specialise
+				if m.specialisation.is_some() {
+					continue;
+				}
+				let mut name: Vec<&str> = path.iter().map(|p| ids.get_str(*p)).collect();
+				name.push(ids.get_str(m.name));
+				let name = ids.id(&name.join("::"));
+				let (raw_design, warnings) = {
+					let mut e = Eval::init(ids, name, globals);
+					e.st.ns_path = path.clone();
+					if !e.eval_block(m, &Eval::block_specs(ns, m)).map_err(|err| located(e.st.ids, err.0, &err.1))? {
+						continue;
+					}
+					/* for (_, v) in e.st.vars.iter() {
+						println!("  {:?}: {:?} = {:?}", ids.get_str(v.name), v.typ, v.value);
+					}*/ 
+					e.st.des.doc = st.doc.clone();
+					let warnings: Vec<String> = e.warnings.iter().map(|w| located(e.st.ids, w.0, &w.1)).collect();
+					(format!("{:?}", e.st.des), warnings)
+				};
+				for w in warnings.iter() {
+					eprintln!("warning: {}", w);
+				}
+				println!("*** MODULE {} ***", ids.get_str(name));
+				println!("");
+				println!("{}", &conv_ids(ids, &raw_design));
+			},
+		}
+	}
+	Ok(())
+}
+
+// A NAME=VALUE define; the value is any integer literal, and defaults to 1. It overrides a global of the same name
+fn parse_define(ids: &mut IdStringDb, define: &str) -> Result<(IdString, BitVector), String> {
+	let (name, value) = match define.find('=') {
+		Some(pos) => (&define[..pos], &define[pos+1..]),
+		None => (define, "1"),
+	};
+	if name.is_empty() {
+		return Err(format!("missing name in define '{}'", define));
+	}
+	let mut tok = Tokeniser::new(ids.id("<command line>"), value.chars());
+	let value = match tok.token(ids) {
+		Ok(Token::IntLiteral(bv)) if tok.skip_whitespace().is_ok() && tok.eof() => bv,
+		_ => return Err(format!("invalid value '{}' for define {}, expected an integer literal", value, name)),
+	};
+	Ok((ids.id(name), value))
+}
+
 fn main() -> Result<(), String> {
 	let args: Vec<String> = env::args().collect();
 	let mut ids = IdStringDb::new();
 	constids::do_ids_init(&mut ids);
-	let filename = args.get(1).unwrap();
+	// usage: [-I include_dir]... [-D NAME[=VALUE]]... file...
+	let mut include_paths = Vec::new();
+	let mut defines = Vec::new();
+	let mut filenames = Vec::new();
+	let mut arg_iter = args.iter().skip(1);
+	while let Some(arg) = arg_iter.next() {
+		if arg == "-I" {
+			include_paths.push(PathBuf::from(arg_iter.next().ok_or(format!("expected directory after -I"))?));
+		} else if let Some(dir) = arg.strip_prefix("-I") {
+			include_paths.push(PathBuf::from(dir));
+		} else if arg == "-D" {
+			let define = arg_iter.next().ok_or(format!("expected NAME=VALUE after -D"))?;
+			defines.push(parse_define(&mut ids, define)?);
+		} else if let Some(define) = arg.strip_prefix("-D") {
+			defines.push(parse_define(&mut ids, define)?);
+		} else {
+			filenames.push(arg.clone());
+		}
+	}
+	let filename = filenames.get(0).ok_or(format!("no input files"))?;
 	let mut f = File::open(filename).map_err(|e| e.to_string())?;
 	let mut buffer = String::new();
 	f.read_to_string(&mut buffer).map_err(|e| e.to_string())?;
+	let parse_err = |ids: &IdStringDb, e: ParserError| format!("{}:{}:{}: {}", ids.get_str(e.file), e.lc.line, e.lc.col, e.msg);
 	let tokeniser = Tokeniser::new(ids.id(filename), buffer.chars());
-	let ps = ParserState::new(tokeniser, &mut ids).map_err(|e| e.msg.to_string())?;
+	let mut ps = ParserState::with_include_paths(tokeniser, &mut ids, include_paths).map_err(|e| parse_err(&ids, e))?;
+	// further files are parsed as if they followed the first
+	for filename in filenames.iter().skip(1) {
+		ps.add_input(PathBuf::from(filename));
+	}
 	let mut p = Parser::new(ps);
-	let sts = p.do_parse(&mut ids).map_err(|e| e.msg.to_string())?;
+	let root = p.do_parse(&mut ids).map_err(|e| parse_err(&ids, e))?;
 	println!("*** RAW AST ***");
-	for st in sts.iter() {
-		let raw_st = format!("{}", st);
-		println!("{}", &conv_ids(&ids, &raw_st));
-	}
-	for st in sts.iter() {
-		if let StatementType::Module(m) = &st.ty {
-			println!("*** MODULE {} ***", ids.get_str(m.name));
-			let raw_design = {
-				let mut e = crate::codegen::eval::Eval::init(&mut ids, m);
-				e.eval_mod(m).map_err(|e| e.1.to_string())?;
-				/* for (_, v) in e.st.vars.iter() {
-					println!("  {:?}: {:?} = {:?}", ids.get_str(v.name), v.typ, v.value);
-				}*/ 
-				println!("");
-				format!("{:?}", e.st.des)
-			};
-			println!("{}", &conv_ids(&ids, &raw_design));
-		}
-	}
+	println!("{}", &conv_ids(&ids, &format!("{}", root)));
+	let globals = Eval::eval_globals_with_defines(&mut ids, &root, &defines).map_err(|e| located(&ids, e.0, &e.1))?;
+	elab_namespace(&mut ids, &globals, &root, &mut Vec::new())?;
 	Ok(())
 }
@@ -5,5 +5,5 @@ pub mod tokeniser;
 
 pub use token::Token;
 pub use tokeniser::{Tokeniser, TokeniserError};
-pub use parser_state::ParserState;
+pub use parser_state::{ParserError, ParserState};
 pub use parser::Parser;
@@ -2,19 +2,23 @@ use crate::ast::*;
 use crate::core::{constids, IdString, IdStringDb};
 use crate::parser::parser_state::*;
 use crate::parser::token::*;
+use rustc_hash::FxHashMap;
 
 pub struct Parser<Iter: Iterator<Item=char>> {
 	state: ParserState<Iter>,
 	// for scope resolution
 	namespace_stack: Vec<Namespace>,
 	statement_stack: Vec<Statement>,
+	// everything declared at namespace level so far, by fully qualified name
+	ns_decls: FxHashMap<IdString, IdentifierType>,
 	// special casing
 	is_interface: bool,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum OpStackItem {
-	Op(Operator),
+	// an operator, and where its symbol(s) are
+	Op(Operator, SrcInfo),
 	LParen,
 	RParen,
 }
@@ -34,16 +38,101 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 			state: state,
 			namespace_stack: Vec::new(),
 			statement_stack: Vec::new(),
+			ns_decls: FxHashMap::default(),
 			is_interface: false,
 		}
 	}
-	pub fn do_parse(&mut self, ids: &mut IdStringDb) ->  Result<Vec<Statement>, ParserError> {
-		// TODO: namespace
-		let mut sts = Vec::new();
+	pub fn do_parse(&mut self, ids: &mut IdStringDb) ->  Result<Namespace, ParserError> {
+		self.namespace_stack.push(Namespace::new(None, AttributeList::new(), self.state.src()));
+		// number of namespaces opened by each '{', as 'namespace a::b {' opens two
+		let mut ns_braces = Vec::new();
 		while self.state.peek().is_some() {
-			sts.push(self.parse_statement(ids, &ScopeLevel { parent: None, entry: &sts })?.unwrap());
+			if !ns_braces.is_empty() && self.state.consume_sym(ids, "}")? {
+				for _ in 0..ns_braces.pop().unwrap() {
+					let mut ns = self.namespace_stack.pop().unwrap();
+					ns.src = self.state.span(ns.src);
+					self.namespace_stack.last_mut().unwrap().content.push(NamespaceItem::Namespace(Box::new(ns)));
+				}
+			} else if self.state.check_kws(&[constids::namespace]) {
+				let ns_src = self.state.src();
+				self.state.consume_kw(ids, constids::namespace)?;
+				let mut count = 0;
+				loop {
+					let name = self.state.expect_ident(ids)?;
+					let qual_name = self.qualify(ids, name);
+					self.ns_decls.insert(qual_name, IdentifierType::Namespace);
+					self.namespace_stack.push(Namespace::new(Some(name), AttributeList::new(), ns_src));
+					count += 1;
+					if !self.state.consume_sym(ids, "::")? {
+						break;
+					}
+				}
+				self.state.expect_sym(ids, "{")?;
+				ns_braces.push(count);
+			} else {
+				let st = self.parse_statement(ids, &ScopeLevel { parent: None, entry: &NullEntry })?.unwrap();
+				let decls = match &st.ty {
+					StatementType::Struct(s) => vec![(s.name, IdentifierType::Type)],
+					StatementType::Using(s) => vec![(s.name, IdentifierType::Type)],
+					StatementType::Typedef(s) => vec![(s.name, IdentifierType::Type)],
+					StatementType::Var(s) => vec![(s.name, IdentifierType::Var)],
+					StatementType::VarList(vs) => vs.iter().map(|s| (s.name, IdentifierType::Var)).collect(),
+					StatementType::Func(s) if !s.templ_args.is_empty() || s.specialisation.is_some() => vec![(s.name, IdentifierType::TemplateFunc)],
+					StatementType::Func(s) => vec![(s.name, IdentifierType::Var)],
+					_ => Vec::new(),
+				};
+				for (name, ty) in decls {
+					let qual_name = self.qualify(ids, name);
+					// a plain overload must not hide a function template of the same name
+					if ty == IdentifierType::Var && self.ns_decls.get(&qual_name) == Some(&IdentifierType::TemplateFunc) {
+						continue;
+					}
+					self.ns_decls.insert(qual_name, ty);
+				}
+				self.namespace_stack.last_mut().unwrap().content.push(NamespaceItem::Stmt(st));
+			}
+		}
+		if !ns_braces.is_empty() {
+			return Err(self.state.err(format!("unexpected end of file inside namespace")));
 		}
-		Ok(sts)
+		let mut root = self.namespace_stack.pop().unwrap();
+		root.src = self.state.span(root.src);
+		Ok(root)
+	}
+	// The fully qualified name of something declared in the current namespace
+	fn qualify(&self, ids: &mut IdStringDb, name: IdString) -> IdString {
+		let mut qual_name = String::new();
+		for ns in self.namespace_stack.iter().filter_map(|ns| ns.name) {
+			qual_name.push_str(ids.get_str(ns));
+			qual_name.push_str("::");
+		}
+		qual_name.push_str(ids.get_str(name));
+		ids.id(&qual_name)
+	}
+	// Look up a possibly qualified name declared at namespace level, searching enclosing namespaces innermost first
+	fn lookup_ns_decl(&self, ids: &IdStringDb, name: IdString) -> Option<IdentifierType> {
+		let path: Vec<IdString> = self.namespace_stack.iter().filter_map(|ns| ns.name).collect();
+		for i in (0..=path.len()).rev() {
+			let mut qual_name = String::new();
+			for ns in path[0..i].iter() {
+				qual_name.push_str(ids.get_str(*ns));
+				qual_name.push_str("::");
+			}
+			qual_name.push_str(ids.get_str(name));
+			if let Some(ty) = ids.get_id(&qual_name).and_then(|id| self.ns_decls.get(&id)) {
+				return Some(*ty);
+			}
+		}
+		None
+	}
+	// Consume the rest of a name qualified by namespaces, returning it as a single identifier
+	fn parse_qualified(&mut self, ids: &mut IdStringDb, first: IdString) -> Result<IdString, ParserError> {
+		let mut name = first;
+		while self.lookup_ns_decl(ids, name) == Some(IdentifierType::Namespace) && self.state.consume_sym(ids, "::")? {
+			let next = self.state.expect_ident(ids)?;
+			name = ids.id(&format!("{}::{}", ids.get_str(name), ids.get_str(next)));
+		}
+		Ok(name)
 	}
 	pub fn parse_attrs(&mut self, ids: &mut IdStringDb, curr_scope: &ScopeLevel) -> Result<AttributeList, ParserError> {
 		let mut attrs = AttributeList::new();
@@ -120,11 +209,35 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 		Ok(args)
 	}
 	pub fn parse_statement(&mut self, ids: &mut IdStringDb, curr_scope: &ScopeLevel) -> Result<Option<Statement>, ParserError> {
+		// doc comments belong to the statement that follows them
+		let doc = self.state.doc_comment();
+		let start = self.state.src();
+		let mut st = self.parse_statement_ty(ids, curr_scope)?;
+		if let Some(st) = &mut st {
+			st.doc = doc;
+			st.src = self.state.span(start);
+			// declarations cover the whole statement, including attributes and template parameters
+			match &mut st.ty {
+				StatementType::Module(m) => m.src = st.src,
+				StatementType::Func(f) => f.src = st.src,
+				StatementType::Struct(s) => s.src = st.src,
+				_ => {},
+			}
+		}
+		Ok(st)
+	}
+	fn parse_statement_ty(&mut self, ids: &mut IdStringDb, curr_scope: &ScopeLevel) -> Result<Option<Statement>, ParserError> {
 		let attrs = self.parse_attrs(ids, curr_scope)?;
 		let tdecl = self.parse_template_decl(ids, curr_scope)?;
+		// template parameters are visible throughout the declaration
+		let templ_params = tdecl.clone();
+		let templ_scope = ScopeLevel { parent: Some(curr_scope), entry: &templ_params };
+		let curr_scope = &templ_scope;
 		use StatementType::*;
 		if self.state.check_sym("{") {
-			Ok(Some(self.parse_block(ids, curr_scope)?))
+			let mut block = self.parse_block(ids, curr_scope)?;
+			block.attrs = attrs;
+			Ok(Some(block))
 		} else if self.state.consume_kw(ids, constids::typedef)? {
 			let ty = self.parse_datatype(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected data type after typedef")))?;
 			let name = self.state.expect_ident(ids)?;
@@ -149,14 +262,37 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 			)))
 		} else if self.state.consume_kw(ids, constids::r#struct)? {
 			let name = self.state.expect_ident(ids)?;
-			// TODO: inheritance
+			let spec = self.parse_specialisation(ids, curr_scope)?;
+			let bases = self.parse_bases(ids, curr_scope)?;
 			let content = self.parse_block(ids, &ScopeLevel { parent: Some(curr_scope), entry: &StructHeaderEntry { name: name } })?;
 			self.state.expect_sym(ids, ";")?;
 			Ok(Some(Statement::new(
 				Struct(StructureDef {
 					name: name,
 					is_interface: false,
+					is_union: false,
+					bases: bases,
 					templ_args: tdecl,
+					specialisation: spec,
+					block: Box::new(content),
+					attrs: attrs.clone(),
+					src: SrcInfo::default(),
+				}), attrs
+			)))
+		} else if self.state.consume_kw(ids, constids::r#union)? {
+			// packed union, all members overlay the same bits
+			let name = self.state.expect_ident(ids)?;
+			let spec = self.parse_specialisation(ids, curr_scope)?;
+			let content = self.parse_block(ids, &ScopeLevel { parent: Some(curr_scope), entry: &StructHeaderEntry { name: name } })?;
+			self.state.expect_sym(ids, ";")?;
+			Ok(Some(Statement::new(
+				Struct(StructureDef {
+					name: name,
+					is_interface: false,
+					is_union: true,
+					bases: Vec::new(),
+					templ_args: tdecl,
+					specialisation: spec,
 					block: Box::new(content),
 					attrs: attrs.clone(),
 					src: SrcInfo::default(),
@@ -164,7 +300,8 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 			)))
 		} else if self.state.consume_kw(ids, constids::r#interface)? {
 			let name = self.state.expect_ident(ids)?;
-			// TODO: inheritance
+			let spec = self.parse_specialisation(ids, curr_scope)?;
+			let bases = self.parse_bases(ids, curr_scope)?;
 			let old_is_if = self.is_interface;
 			self.is_interface = true;
 			let content = self.parse_block(ids, &ScopeLevel { parent: Some(curr_scope), entry: &StructHeaderEntry { name: name } })?;
@@ -174,7 +311,10 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 				Struct(StructureDef {
 					name: name,
 					is_interface: true,
+					is_union: false,
+					bases: bases,
 					templ_args: tdecl,
+					specialisation: spec,
 					block: Box::new(content),
 					attrs: attrs.clone(),
 					src: SrcInfo::default(),
@@ -201,22 +341,96 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 			)))
 		} else if self.state.consume_kw(ids, constids::r#for)? {
 			let is_meta = self.state.consume_kw(ids, constids::r#meta)?;
-			// TODO: range based for
 			self.state.expect_sym(ids, "(")?;
-			let init = self.parse_statement(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected statement after for(")))?;
+			// a declaration followed by ':' is a range based for
+			self.state.enter_ambig();
+			let range_decl = match self.parse_datatype(ids, curr_scope)? {
+				Some(typ) => match self.state.consume_ident(ids)? {
+					Some(name) if self.state.consume_sym(ids, ":")? => Some((typ, name)),
+					_ => None,
+				},
+				None => None,
+			};
+			if let Some((typ, name)) = range_decl {
+				self.state.ambig_success(ids)?;
+				let range = self.parse_expression(ids, curr_scope, false)?;
+				self.state.expect_sym(ids, ")")?;
+				let body = self.parse_statement(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected statement after for()")))?;
+				Ok(Some(Statement::new(
+					RangeFor(RangeForLoop {
+						var_name: name,
+						var_type: typ,
+						range: range,
+						body: Box::new(body),
+						is_meta: is_meta
+					}), attrs
+				)))
+			} else {
+				self.state.ambig_failure(ids)?;
+				let init = self.parse_statement(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected statement after for(")))?;
+				let cond = self.parse_expression(ids, curr_scope, false)?;
+				self.state.expect_sym(ids, ";")?;
+				let incr = self.parse_expression(ids, curr_scope, false)?;
+				self.state.expect_sym(ids, ")")?;
+				let body = self.parse_statement(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected statement after for()")))?;
+				Ok(Some(Statement::new(
+					For(ForLoop {
+						init: Box::new(init),
+						cond: cond,
+						incr: incr,
+						body: Box::new(body),
+						is_meta: is_meta
+					}), attrs
+				)))
+			}
+		} else if self.state.consume_kw(ids, constids::r#while)? {
+			let is_meta = self.state.consume_kw(ids, constids::r#meta)?;
+			self.state.expect_sym(ids, "(")?;
+			let cond = self.parse_expression(ids, curr_scope, false)?;
+			self.state.expect_sym(ids, ")")?;
+			let body = self.parse_statement(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected statement after while()")))?;
+			Ok(Some(Statement::new(
+				While(WhileLoop {
+					cond: cond,
+					body: Box::new(body),
+					is_meta: is_meta,
+					is_do_while: false,
+				}), attrs
+			)))
+		} else if self.state.consume_kw(ids, constids::r#do)? {
+			let is_meta = self.state.consume_kw(ids, constids::r#meta)?;
+			let body = self.parse_statement(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected statement after do")))?;
+			self.state.expect_kw(ids, constids::r#while)?;
+			self.state.expect_sym(ids, "(")?;
 			let cond = self.parse_expression(ids, curr_scope, false)?;
-			let incr = self.parse_expression(ids, curr_scope, false)?;
 			self.state.expect_sym(ids, ")")?;
-			let body = self.parse_statement(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected statement after for()")))?;
+			self.state.expect_sym(ids, ";")?;
 			Ok(Some(Statement::new(
-				For(ForLoop {
-					init: Box::new(init),
+				While(WhileLoop {
 					cond: cond,
-					incr: incr,
 					body: Box::new(body),
-					is_meta: is_meta
+					is_meta: is_meta,
+					is_do_while: true,
+				}), attrs
+			)))
+		} else if self.state.consume_kw(ids, constids::r#switch)? {
+			self.state.expect_sym(ids, "(")?;
+			let value = self.parse_expression(ids, curr_scope, false)?;
+			self.state.expect_sym(ids, ")")?;
+			let body = self.parse_block(ids, curr_scope)?;
+			Ok(Some(Statement::new(
+				Switch(SwitchStatement {
+					value: value,
+					body: Box::new(body),
 				}), attrs
 			)))
+		} else if self.state.consume_kw(ids, constids::r#case)? {
+			let value = self.parse_expression(ids, curr_scope, false)?;
+			self.state.expect_sym(ids, ":")?;
+			Ok(Some(Statement::new(Case(value), attrs)))
+		} else if self.state.consume_kw(ids, constids::default)? {
+			self.state.expect_sym(ids, ":")?;
+			Ok(Some(Statement::new(Default, attrs)))
 		} else if self.state.consume_kw(ids, constids::r#return)? {
 			let expr = self.parse_expression(ids, curr_scope, false)?;
 			self.state.expect_sym(ids, ";")?;
@@ -229,8 +443,23 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 		} else if self.state.consume_kw(ids, constids::r#continue)? {
 			self.state.expect_sym(ids, ";")?;
 			Ok(Some(Statement::new(Continue, attrs)))
+		} else if self.state.consume_kw(ids, constids::static_assert)? {
+			self.state.expect_sym(ids, "(")?;
+			let cond = self.parse_expression(ids, curr_scope, false)?;
+			let msg = if self.state.consume_sym(ids, ",")? {
+				match self.state.expect_literal(ids)? {
+					Token::StrLiteral(s) => Some(s),
+					t => return Err(self.state.err(format!("expected string literal as static_assert message, got {:?}", t))),
+				}
+			} else {
+				None
+			};
+			self.state.expect_sym(ids, ")")?;
+			self.state.expect_sym(ids, ";")?;
+			Ok(Some(Statement::new(StaticAssert(cond, msg), attrs)))
 		} else if self.state.consume_kw(ids, constids::r#block)? {
 			let module_name = self.state.expect_ident(ids)?;
+			let spec = self.parse_specialisation(ids, curr_scope)?;
 			self.state.expect_sym(ids, "(")?;
 			let inputs = self.parse_arglist(ids, curr_scope)?;
 			self.state.expect_sym(ids, ")")?;
@@ -258,6 +487,7 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 			Ok(Some(Statement::new(Module(statement::Module {
 				name: module_name,
 				templ_args: tdecl,
+				specialisation: spec,
 				attrs: attrs.clone(),
 				content: Box::new(content),
 				ports: io,
@@ -282,39 +512,74 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 			if let Some(typ) = typ {
 				self.state.ambig_success(ids)?;
 				// variable, or function
-				// TODO: multiple variables in a list - need to refactor to produce a list of statements
-				let name = self.state.expect_ident(ids)?;
+				let mut name = if self.state.consume_kw(ids, constids::operator)? {
+					let op_name = self.parse_operator_name(ids)?;
+					if !self.state.check_sym("(") {
+						return Err(self.state.err(format!("expected '(' after {}", ids.get_str(op_name))));
+					}
+					op_name
+				} else {
+					self.state.expect_ident(ids)?
+				};
+				let spec = self.parse_specialisation(ids, curr_scope)?;
+				if spec.is_some() && !self.state.check_sym("(") {
+					return Err(self.state.err(format!("expected '(' after specialisation of {}", ids.get_str(name))));
+				}
 				if self.state.consume_sym(ids, "(")? {
 					// function
 					let args = self.parse_arglist(ids, curr_scope)?;
 					self.state.expect_sym(ids, ")")?;
+					let is_const = self.state.consume_kw(ids, constids::r#const)?;
 					let content = self.parse_statement(ids, curr_scope)?.unwrap();
-					self.state.expect_sym(ids, ";")?;
+					self.state.consume_sym(ids, ";")?;
 					Ok(Some(Statement::new(Func(
 						Function {
 							attrs: attrs.clone(),
 							name: name,
 							ret_type: typ,
+							is_const: is_const,
 							func_args: args,
 							templ_args: tdecl,
+							specialisation: spec,
 							content: Box::new(content),
 							src: SrcInfo::default(),
 						}
 					), attrs)))
 				} else {
-					// variable
-					let mut init = None;
-					if self.state.consume_sym(ids, "=")? {
-						init = Some(self.parse_expression(ids, curr_scope, false)?);
-					}
-					self.state.expect_sym(ids, ";")?;
-					Ok(Some(Statement::new(Var(
-						VariableDecl {
+					// variable, or a list of variables sharing a base type
+					let mut decls = Vec::new();
+					loop {
+						// C-style array dimensions after the name only apply to this variable
+						let mut dims = Vec::new();
+						while self.state.consume_sym(ids, "[")? {
+							dims.extend(self.parse_expression_list(ids, curr_scope, "]")?);
+							self.state.expect_sym(ids, "]")?;
+						}
+						let ty = if dims.is_empty() {
+							typ.clone()
+						} else {
+							DataType { is_const: typ.is_const, is_static: typ.is_static, typ: DataTypes::Array(ArrayType { base: Box::new(typ.clone()), dims }) }
+						};
+						let mut init = None;
+						if self.state.consume_sym(ids, "=")? {
+							init = Some(self.parse_expression(ids, curr_scope, false)?);
+						}
+						decls.push(VariableDecl {
 							name: name,
-							ty: typ,
+							ty: ty,
 							init: init,
+						});
+						if !self.state.consume_sym(ids, ",")? {
+							break;
 						}
-					), attrs)))
+						name = self.state.expect_ident(ids)?;
+					}
+					self.state.expect_sym(ids, ";")?;
+					if decls.len() == 1 {
+						Ok(Some(Statement::new(Var(decls.pop().unwrap()), attrs)))
+					} else {
+						Ok(Some(Statement::new(VarList(decls), attrs)))
+					}
 				}
 			} else {
 				self.state.ambig_failure(ids)?;
@@ -325,6 +590,44 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 			}
 		}
 	}
+	// The name of an overloaded operator function, such as operator+ or operator[]
+	pub fn parse_operator_name(&mut self, ids: &mut IdStringDb) -> Result<IdString, ParserError> {
+		let token = if self.state.consume_sym(ids, "[")? {
+			self.state.expect_sym(ids, "]")?;
+			"[]"
+		} else {
+			let mut found = None;
+			for sym in Operator::SYMBOLS.iter().filter(|s| **s != "?") {
+				if self.state.consume_sym(ids, sym)? {
+					found = Some(*sym);
+					break;
+				}
+			}
+			found.ok_or_else(|| self.state.err(format!("expected operator after 'operator'")))?
+		};
+		Ok(ids.id(&format!("operator{}", token)))
+	}
+	// Template arguments following the name of a declaration make it a specialisation
+	pub fn parse_specialisation(&mut self, ids: &mut IdStringDb, curr_scope: &ScopeLevel) -> Result<Option<Vec<TemplateValue>>, ParserError> {
+		if self.state.check_sym("<") {
+			Ok(Some(self.parse_template_vals(ids, curr_scope)?))
+		} else {
+			Ok(None)
+		}
+	}
+	// The optional list of types a structure or interface inherits from
+	pub fn parse_bases(&mut self, ids: &mut IdStringDb, curr_scope: &ScopeLevel) -> Result<Vec<DataType>, ParserError> {
+		let mut bases = Vec::new();
+		if self.state.consume_sym(ids, ":")? {
+			loop {
+				bases.push(self.parse_datatype(ids, curr_scope)?.ok_or_else(|| self.state.err(format!("expected base type after ':'")))?);
+				if !self.state.consume_sym(ids, ",")? {
+					break;
+				}
+			}
+		}
+		Ok(bases)
+	}
 	pub fn parse_template_vals(&mut self, ids: &mut IdStringDb, curr_scope: &ScopeLevel) -> Result<Vec<TemplateValue>, ParserError> {
 		let mut vals = Vec::new();
 		if self.state.consume_sym(ids, "<")? {
@@ -403,8 +706,9 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 		} else if self.state.check_kws(INTEGRAL_TYPES) {
 			DataTypes::Integer(self.parse_integral_type(ids, curr_scope)?)
 		} else if let Some(ident) = self.state.consume_ident(ids,)? {
+			let ident = self.parse_qualified(ids, ident)?;
 			// is_typename forces identifier to be a type
-			if is_typename /*|| curr_scope.is_type(ident)*/ {
+			if is_typename || curr_scope.is_type(ident) || self.lookup_ns_decl(ids, ident) == Some(IdentifierType::Type) {
 				// TODO: template arguments
 				DataTypes::User(UserType{name: ident, args: self.parse_template_vals(ids, curr_scope)?})
 			} else {
@@ -433,13 +737,14 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 	pub fn pop_op_stack(&mut self, op_stack: &mut Vec<OpStackItem>, expr_stack: &mut Vec<Expression>) -> Result<(), ParserError> {
 		let op = op_stack.pop().ok_or_else(|| self.state.err(format!("operation stack underflow")))?;
 		match op {
-			OpStackItem::Op(o) => {
+			OpStackItem::Op(o, op_src) => {
 				let mut args = Vec::new();
 				for _ in 0..o.arg_count() {
 					args.push(expr_stack.pop().ok_or_else(|| self.state.err(format!("too few arguments for operator {}", o.token())))?);
 				}
 				args.reverse();
-				expr_stack.push(Expression::new(ExprType::Op(o, args)));
+				let src = args.iter().fold(op_src, |src, a| src.join(&a.src));
+				expr_stack.push(Expression::new_full(ExprType::Op(o, args), AttributeList::new(), src));
 			}
 			_ => {}
 		}
@@ -451,19 +756,72 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 		let mut last_was_operator = true;
 		let mut op_stack : Vec<OpStackItem> = Vec::new();
 		let mut expr_stack : Vec<Expression> = Vec::new();
+		// Set the location of the expression just pushed, which started at 'start'
+		let located = |p: &Self, expr_stack: &mut Vec<Expression>, start: SrcInfo| {
+			if let Some(e) = expr_stack.last_mut() {
+				e.src = p.state.span(start);
+			}
+		};
 		loop {
+			let start = self.state.src();
 			if let Some(tok) = self.state.consume_literal(ids)? {
 				last_was_operator = false;
 				match tok {
 					Token::IntLiteral(bv) => {
 						expr_stack.push(Expression::new(Literal(bv)));
 					}
+					Token::ChrLiteral(s) => {
+						// characters are 8-bit constants
+						let mut chars = s.chars();
+						match (chars.next(), chars.next()) {
+							(Some(c), None) if (c as u32) < 256 => expr_stack.push(Expression::from_u64(c as u64, 8)),
+							_ => { return Err(self.state.err(format!("character literal '{}' must be a single 8-bit character", s))); }
+						}
+					}
+					Token::StrLiteral(s) => {
+						expr_stack.push(Expression::new(StrLiteral(s)));
+					}
 					_ => { return Err(self.state.err(format!("unsupported literal {:?}", tok))); }
 				}
+				located(self, &mut expr_stack, start);
 			} else if let Some(id) = self.state.consume_ident(ids)? {
 				last_was_operator = false;
+				let id = self.parse_qualified(ids, id)?;
+				let id_src = self.state.span(start);
+				let shadowed = curr_scope.is_var(id) || self.lookup_ns_decl(ids, id) == Some(IdentifierType::Var);
+				// cat(...) and rep<N>(...) are intrinsics, unless shadowed by a variable
+				if id == constids::cat && !shadowed && self.state.consume_sym(ids, "(")? {
+					expr_stack.push(Expression::new(Cat(self.parse_expression_list(ids, curr_scope, ")")?)));
+					self.state.expect_sym(ids, ")")?;
+					located(self, &mut expr_stack, start);
+					continue;
+				} else if id == constids::rep && !shadowed && self.state.consume_sym(ids, "<")? {
+					let count = self.parse_expression(ids, curr_scope, true)?;
+					self.state.expect_sym(ids, ">")?;
+					self.state.expect_sym(ids, "(")?;
+					let value = self.parse_expression(ids, curr_scope, false)?;
+					self.state.expect_sym(ids, ")")?;
+					expr_stack.push(Expression::new(Rep(Replication { count: Box::new(count), value: Box::new(value) })));
+					located(self, &mut expr_stack, start);
+					continue;
+				}
+				if !shadowed && self.lookup_ns_decl(ids, id) == Some(IdentifierType::TemplateFunc) && self.state.check_sym("<") {
+					// explicit template arguments to a function template
+					let templ_vals = self.parse_template_vals(ids, curr_scope)?;
+					self.state.expect_sym(ids, "(")?;
+					expr_stack.push(Expression::new(Func(
+						FuncCall {
+							target: Box::new(Expression::new_full(Variable(id), AttributeList::new(), id_src)),
+							targs: templ_vals,
+							args: self.parse_expression_list(ids, curr_scope, ")")?
+						}
+					)));
+					self.state.expect_sym(ids, ")")?;
+					located(self, &mut expr_stack, start);
+					continue;
+				}
 				// self.resolve_ident(curr_scope, id)?;
-				expr_stack.push(Expression::new(Variable(id)));
+				expr_stack.push(Expression::new_full(Variable(id), AttributeList::new(), id_src));
 			} else if self.state.consume_sym(ids, "(")? {
 				if last_was_operator {
 					// parentheses
@@ -471,15 +829,16 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 				} else {
 					// function call
 					let target = expr_stack.pop().unwrap();
-					let templ_vals = self.parse_template_vals(ids, curr_scope)?;
+					let call_start = target.src;
 					expr_stack.push(Expression::new(Func(
 						FuncCall {
 							target: Box::new(target),
-							targs: templ_vals,
+							targs: Vec::new(),
 							args: self.parse_expression_list(ids, curr_scope, ")")?
 						}
 					)));
 					self.state.expect_sym(ids, ")")?;
+					located(self, &mut expr_stack, call_start);
 				}
 				last_was_operator = true;
 			} else if self.state.check_sym(")") {
@@ -495,25 +854,45 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 					assert_eq!(op_stack.pop(), Some(OpStackItem::LParen));
 				}
 				last_was_operator = false;
-			} else if self.state.check_sym("{") {
+			} else if self.state.consume_sym(ids, "{")? {
 				// initialiser list
 				expr_stack.push(Expression::new(List(self.parse_expression_list(ids, curr_scope, "}")?)));
 				self.state.expect_sym(ids, "}")?;
+				located(self, &mut expr_stack, start);
 				last_was_operator = false;
-			} else if self.state.check_sym(".") {
+			} else if self.state.consume_sym(ids, ".")? {
 				let prev = expr_stack.pop().ok_or_else(|| self.state.err(format!("expected expression before .")))?;
+				let prev_src = prev.src;
 				expr_stack.push(Expression::new(MemberAccess(Box::new(prev), self.state.expect_ident(ids)?)));
+				located(self, &mut expr_stack, prev_src);
 				last_was_operator = false;
-			} else if self.state.check_sym("[") {
+			} else if self.state.consume_sym(ids, "[")? {
 				let prev = expr_stack.pop().ok_or_else(|| self.state.err(format!("expected expression before [")))?;
+				let prev_src = prev.src;
 				expr_stack.push(Expression::new(ArrAcc(ArrayAccess {
 					array: Box::new(prev),
 					indices: self.parse_expression_list(ids, curr_scope, "]")?
 				})));
+				self.state.expect_sym(ids, "]")?;
+				located(self, &mut expr_stack, prev_src);
 				last_was_operator = false;
 			} else if is_templ_arg && self.state.check_sym(">") && !op_stack.iter().any(|s| match s { OpStackItem::LParen => true, _ => false }) {
 				// special case for end of template argument list (only when no parentheses in stack)
 				break;
+			} else if !last_was_operator && self.state.consume_sym(ids, "?")? {
+				// conditional, the middle operand is parsed separately as it is delimited by the ':'
+				let op = Operator::Ternary;
+				while let Some(OpStackItem::Op(top, _)) = op_stack.last() {
+					if op.precedence() < top.precedence() {
+						self.pop_op_stack(&mut op_stack, &mut expr_stack)?;
+					} else {
+						break;
+					}
+				}
+				expr_stack.push(self.parse_expression(ids, curr_scope, false)?);
+				self.state.expect_sym(ids, ":")?;
+				op_stack.push(OpStackItem::Op(op, self.state.span(start)));
+				last_was_operator = true;
 			} else if let Some(op_sym) = self.state.consume_any_sym(ids, Operator::SYMBOLS)? {
 				let op = if last_was_operator {
 					// unary prefix
@@ -521,11 +900,11 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 				} else {
 					// binary or unary postfix
 					Operator::lookup(op_sym, 2, false).or_else(|| Operator::lookup(op_sym, 1, true))
-				}.unwrap();
+				}.ok_or_else(|| self.state.err(format!("unexpected '{}' in expression", op_sym)))?;
 				// shunting yard
 				while !op_stack.is_empty() {
 					match op_stack.last().unwrap() {
-						OpStackItem::Op(top) => {
+						OpStackItem::Op(top, _) => {
 							if (op.is_right_assoc() && op.precedence() < top.precedence())
 								|| (!op.is_right_assoc() && op.precedence() <= top.precedence()) {
 								self.pop_op_stack(&mut op_stack, &mut expr_stack)?;
@@ -536,8 +915,9 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 						_ => break,
 					}
 				}
-				op_stack.push(OpStackItem::Op(op));
-				last_was_operator = true;
+				op_stack.push(OpStackItem::Op(op, self.state.span(start)));
+				// a postfix operator is followed by a binary operator, not an operand
+				last_was_operator = !op.is_postfix();
 			} else {
 				break;
 			}
@@ -545,7 +925,7 @@ impl <Iter: Iterator<Item=char>> Parser<Iter> {
 		// Finalise stacked operations
 		while !op_stack.is_empty() {
 			match op_stack.last().unwrap() {
-				OpStackItem::Op(_) => self.pop_op_stack(&mut op_stack, &mut expr_stack)?,
+				OpStackItem::Op(..) => self.pop_op_stack(&mut op_stack, &mut expr_stack)?,
 				_ => { return Err(self.state.err(format!("mismatched parentheses in expression"))) }
 			}
 		}
@@ -602,6 +982,20 @@ pub mod test {
 		Ok(())
 	}
 
+	#[test]
+	fn doc_comments() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("/// the answer
+			/// to everything
+			int x = 42; // not documentation
+			int y = 1; /* trailing */
+		")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		assert_eq!(sts[0].doc.as_deref(), Some("the answer\nto everything"));
+		assert_eq!(sts[1].doc, None);
+		Ok(())
+	}
+
 	#[test]
 	fn attrs() -> Result<(), ParserError> {
 		let (mut ids, mut p, _r) = setup("[[attr=11]] [[another_attr]]")?;
@@ -628,6 +1022,253 @@ pub mod test {
 		Ok(())
 	}
 
+	#[test]
+	fn union_decl() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("union insn { unsigned<32> raw; signed<16> half; }; insn i;")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[0].ty {
+			StatementType::Struct(sd) => {
+				assert_eq!(sd.name, ids.id("insn"));
+				assert!(sd.is_union);
+				assert_eq!(sd.block.num_children(), 2);
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		match &sts[1].ty {
+			StatementType::Var(v) => assert_eq!(v.ty.typ, DataTypes::User(UserType { name: ids.id("insn"), args: vec![] })),
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn while_loops() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("while (i < 4) i++; do meta { i--; } while (i > 0);")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[0].ty {
+			StatementType::While(w) => {
+				assert!(!w.is_meta && !w.is_do_while);
+				assert_eq!(w.cond.ty, ExprType::Op(Operator::Lt, vec![Expression::new(ExprType::Variable(ids.id("i"))), Expression::from_u64(4, 64)]));
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		match &sts[1].ty {
+			StatementType::While(w) => assert!(w.is_meta && w.is_do_while),
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn decl_lists() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("unsigned<8> a = 0, b, c[4] = {1, 2}, d[2][3]; int x;")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		let u8_type = DataType { is_const: false, is_static: false, typ: DataTypes::Integer(IntegerType { width: Expression::from_u64(8, 64), is_signed: Expression::from_u64(0, 1) }) };
+		match &sts[0].ty {
+			StatementType::VarList(vs) => {
+				assert_eq!(vs.iter().map(|v| v.name).collect::<Vec<_>>(), vec![ids.id("a"), ids.id("b"), ids.id("c"), ids.id("d")]);
+				assert_eq!(vs[0].init, Some(Expression::from_u64(0, 64)));
+				assert_eq!(vs[1].ty, u8_type);
+				assert_eq!(vs[1].init, None);
+				match &vs[2].ty.typ {
+					DataTypes::Array(a) => assert_eq!((&*a.base, a.dims.len()), (&u8_type, 1)),
+					other => panic!("unexpected {:?}", other),
+				}
+				assert!(matches!(&vs[2].init, Some(Expression { ty: ExprType::List(l), .. }) if l.len() == 2));
+				match &vs[3].ty.typ {
+					DataTypes::Array(a) => assert_eq!(a.dims, vec![Expression::from_u64(2, 64), Expression::from_u64(3, 64)]),
+					other => panic!("unexpected {:?}", other),
+				}
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		assert!(matches!(&sts[1].ty, StatementType::Var(_)));
+		Ok(())
+	}
+
+	#[test]
+	fn range_for() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("for meta (auto x : arr) s += x; for (auto& y : arr) y++; for (i = 0; i < 2; i++) ;")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[0].ty {
+			StatementType::RangeFor(f) => {
+				assert!(f.is_meta);
+				assert_eq!(f.var_name, ids.id("x"));
+				assert_eq!(f.var_type.typ, DataTypes::Auto);
+				assert_eq!(f.range.ty, ExprType::Variable(ids.id("arr")));
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		match &sts[1].ty {
+			StatementType::RangeFor(f) => assert!(!f.is_meta && matches!(&f.var_type.typ, DataTypes::Reference(_))),
+			other => panic!("unexpected {:?}", other)
+		}
+		assert!(matches!(&sts[2].ty, StatementType::For(_)));
+		Ok(())
+	}
+
+	#[test]
+	fn inheritance() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("struct A { int x; }; struct C { int y; }; struct B : A, C { int z; }; interface J { input int w; }; interface I : J { input int v; };")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[2].ty {
+			StatementType::Struct(s) => assert_eq!(s.bases.iter().map(|b| b.typ.clone()).collect::<Vec<_>>(), vec![
+				DataTypes::User(UserType { name: ids.id("A"), args: vec![] }),
+				DataTypes::User(UserType { name: ids.id("C"), args: vec![] }),
+			]),
+			other => panic!("unexpected {:?}", other)
+		}
+		match &sts[4].ty {
+			StatementType::Struct(s) => assert!(s.is_interface && s.bases.len() == 1),
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn operator_overload() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("struct c { int re; int operator[](int i) { return re; } }; c operator+(c a, c b) { return a; } int x = a + b;")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[1].ty {
+			StatementType::Func(f) => assert_eq!(f.name, ids.id("operator+")),
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn const_functions() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("int get(const int& a) const { return a; } void set(int& a) { a = 1; }")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match (&sts[0].ty, &sts[1].ty) {
+			(StatementType::Func(get), StatementType::Func(set)) => {
+				assert!(get.is_const && !set.is_const);
+				assert!(get.func_args[0].data_type.is_const);
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn templates() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("template <typename T, int N = 4> struct s { T x[N]; }; template <typename T> struct s<T, 8> { T y; }; template <typename T> T f(T a) { return a; } int x = f<int>(3);")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[1].ty {
+			StatementType::Struct(sd) => {
+				assert_eq!(sd.templ_args.len(), 1);
+				let spec = sd.specialisation.as_ref().unwrap();
+				assert_eq!(spec[0], TemplateValue::Typ(DataType { typ: DataTypes::User(UserType { name: ids.id("T"), args: Vec::new() }), is_const: false, is_static: false }));
+				assert_eq!(spec[1], TemplateValue::Expr(Expression::from_u64(8, 64)));
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		match &sts[3].ty {
+			StatementType::Var(v) => match &v.init.as_ref().unwrap().ty {
+				ExprType::Func(fc) => {
+					assert_eq!(fc.target.ty, ExprType::Variable(ids.id("f")));
+					assert_eq!(fc.targs.len(), 1);
+					assert_eq!(fc.args, vec![Expression::from_u64(3, 64)]);
+				},
+				other => panic!("unexpected {:?}", other)
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn static_assert() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("static_assert(N > 0, \"N must be positive\"); static_assert(1);")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[0].ty {
+			StatementType::StaticAssert(e, msg) => {
+				assert!(matches!(e.ty, ExprType::Op(Operator::Gt, _)));
+				assert_eq!(msg.as_deref(), Some("N must be positive"));
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		assert_eq!(sts[1].ty, StatementType::StaticAssert(Expression::from_u64(1, 64), None));
+		Ok(())
+	}
+
+	#[test]
+	fn str_literals() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("x = 'a'; y = \"a\\tb\";")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match (&sts[0].ty, &sts[1].ty) {
+			(StatementType::Expr(x), StatementType::Expr(y)) => {
+				assert!(matches!(&x.ty, ExprType::Op(Operator::Assign, args) if args[1] == Expression::from_u64(97, 8)));
+				assert!(matches!(&y.ty, ExprType::Op(Operator::Assign, args) if args[1].ty == ExprType::StrLiteral("a\tb".to_string())));
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		let (mut ids, mut p, _r) = setup("x = 'ab';")?;
+		match p.do_parse(&mut ids) {
+			Err(e) => assert!(e.msg.contains("single 8-bit character")),
+			Ok(_) => panic!("expected an error"),
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn spans() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("x = a +
+		foo(b)[1];")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		let span = |src: SrcInfo| ((src.start.line, src.start.col), (src.end.line, src.end.col));
+		assert_eq!(ids.get_str(sts[0].src.file), "<test>");
+		assert_eq!(span(sts[0].src), ((1, 1), (2, 13)));
+		match &sts[0].ty {
+			StatementType::Expr(e) => {
+				assert_eq!(span(e.src), ((1, 1), (2, 12)));
+				match &e.ty {
+					ExprType::Op(Operator::Assign, args) => {
+						assert_eq!(span(args[0].src), ((1, 1), (1, 2)));
+						match &args[1].ty {
+							ExprType::Op(Operator::Add, add_args) => {
+								assert_eq!(span(args[1].src), ((1, 5), (2, 12)));
+								assert_eq!(span(add_args[1].src), ((2, 3), (2, 12)));
+							},
+							other => panic!("unexpected {:?}", other)
+						}
+					},
+					other => panic!("unexpected {:?}", other)
+				}
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn switch_case() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("switch (op) { case 1: case 2: x = 1; break; default: x = 0; }")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		match &sts[0].ty {
+			StatementType::Switch(sw) => {
+				assert_eq!(sw.value.ty, ExprType::Variable(ids.id("op")));
+				assert_eq!(sw.body.num_children(), 6);
+				assert_eq!(sw.body.child(1).ty, StatementType::Case(Expression::from_u64(2, 64)));
+				assert_eq!(sw.body.child(4).ty, StatementType::Default);
+			},
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
 	#[test]
 	fn basic_expr() -> Result<(), ParserError> {
 		use ExprType::*;
@@ -646,4 +1287,76 @@ pub mod test {
 		);
 		Ok(())
 	}
+
+	#[test]
+	fn ternary_expr() -> Result<(), ParserError> {
+		use ExprType::*;
+		let (mut ids, mut p, _r) = setup("x = a || b ? 1 : c ? 2 + 3 : 4")?;
+		let var = |ids: &mut IdStringDb, n: &str| Expression::new(Variable(ids.id(n)));
+		assert_eq!(p.parse_expression(&mut ids, &ScopeLevel { parent: None, entry: &NullEntry }, false)?,
+			Expression::new(Op(Operator::Assign, vec![
+				var(&mut ids, "x"),
+				Expression::new(Op(Operator::Ternary, vec![
+					Expression::new(Op(Operator::LogOr, vec![var(&mut ids, "a"), var(&mut ids, "b")])),
+					Expression::from_u64(1, 64),
+					Expression::new(Op(Operator::Ternary, vec![
+						var(&mut ids, "c"),
+						Expression::new(Op(Operator::Add, vec![Expression::from_u64(2, 64), Expression::from_u64(3, 64)])),
+						Expression::from_u64(4, 64),
+					])),
+				])),
+			]))
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn cat_rep() -> Result<(), ParserError> {
+		use ExprType::*;
+		let (mut ids, mut p, _r) = setup("cat(a, rep<2>(b)) = x; int cat; cat(1);")?;
+		let root = p.do_parse(&mut ids)?;
+		let sts = root.statements();
+		let var = |ids: &mut IdStringDb, n: &str| Expression::new(Variable(ids.id(n)));
+		assert_eq!(sts[0].ty, StatementType::Expr(Expression::new(Op(Operator::Assign, vec![
+			Expression::new(Cat(vec![
+				var(&mut ids, "a"),
+				Expression::new(Rep(Replication { count: Box::new(Expression::from_u64(2, 64)), value: Box::new(var(&mut ids, "b")) })),
+			])),
+			var(&mut ids, "x"),
+		]))));
+		// a variable shadows the intrinsic
+		match &sts[2].ty {
+			StatementType::Expr(e) => assert!(matches!(e.ty, Func(_))),
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn namespaces() -> Result<(), ParserError> {
+		let (mut ids, mut p, _r) = setup("namespace a { struct s { int x; }; namespace b { s y; } } namespace a::b { int z = a::b::y; } a::s w;")?;
+		let root = p.do_parse(&mut ids)?;
+		assert_eq!(root.content.len(), 3);
+		match &root.content[1] {
+			NamespaceItem::Namespace(a) => {
+				assert_eq!(a.name, Some(ids.id("a")));
+				match &a.content[0] {
+					NamespaceItem::Namespace(b) => {
+						assert_eq!(b.name, Some(ids.id("b")));
+						match &b.statements()[0].ty {
+							StatementType::Var(v) => assert_eq!(v.init, Some(Expression::new(ExprType::Variable(ids.id("a::b::y"))))),
+							other => panic!("unexpected {:?}", other)
+						}
+					}
+					_ => panic!("expected namespace b")
+				}
+			},
+			_ => panic!("expected namespace a")
+		}
+		match &root.statements()[0].ty {
+			StatementType::Var(v) => assert_eq!(v.ty.typ, DataTypes::User(UserType { name: ids.id("a::s"), args: vec![] })),
+			other => panic!("unexpected {:?}", other)
+		}
+		Ok(())
+	}
 }
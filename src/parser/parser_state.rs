@@ -1,15 +1,41 @@
+use std::cmp::max;
 use std::collections::VecDeque;
+use std::fs;
+use std::iter::once;
+use std::path::PathBuf;
+use rustc_hash::FxHashSet;
 
-use crate::ast::LineCol;
-use crate::core::{IdString, IdStringDb};
+use crate::ast::{LineCol, SrcInfo};
+use crate::core::{constids, IdString, IdStringDb};
 use crate::parser::{Token, Tokeniser, TokeniserError};
 use Token::*;
 
 pub struct ParserState<Iter: Iterator<Item=char>>  {
 	tokeniser: Tokeniser<Iter>,
+	// Tokens and where they start
 	toks: VecDeque<(Token, LineCol)>,
+	// The doc comment before and full location of each token in toks
+	info: VecDeque<TokenInfo>,
 	ptr: usize,
+	// End of the last token removed from toks
+	popped_end: LineCol,
 	ambig_stack: Vec<usize>,
+	// Files being read because of an #include or import, innermost last
+	includes: Vec<(Tokeniser<std::vec::IntoIter<char>>, PathBuf)>,
+	// Further top level files, read once the main one is finished
+	inputs: VecDeque<PathBuf>,
+	// Every file read so far, as each is only included once
+	included: FxHashSet<PathBuf>,
+	include_paths: Vec<PathBuf>,
+	root_path: PathBuf,
+	root_done: bool,
+	// A doc comment before an #include or import, for the next token without one of its own
+	pending_doc: Option<String>,
+}
+
+struct TokenInfo {
+	doc: Option<String>,
+	src: SrcInfo,
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -20,6 +46,13 @@ pub struct ParserError {
 }
 
 impl ParserError {
+	pub fn at(src: SrcInfo, msg: String) -> ParserError {
+		ParserError {
+			file: src.file,
+			lc: src.start,
+			msg: msg,
+		}
+	}
 	pub fn from_tok(e: TokeniserError) -> ParserError {
 		ParserError {
 			file: e.file,
@@ -29,34 +62,173 @@ impl ParserError {
 	}
 }
 
+// Read the next token from a file, with its preceding doc comment and location
+fn read_token<Iter: Iterator<Item=char>>(tok: &mut Tokeniser<Iter>, ids: &mut IdStringDb) -> Result<Option<(Token, TokenInfo)>, ParserError> {
+	// so that trailing whitespace and comments aren't mistaken for another token
+	tok.skip_whitespace().or_else(|e| Err(ParserError::from_tok(e)))?;
+	if tok.eof() {
+		return Ok(None);
+	}
+	let start = tok.linecol();
+	let next_tok = tok.token(ids).or_else(|e| Err(ParserError::from_tok(e)))?;
+	let src = SrcInfo { file: tok.file(), start: start, end: tok.linecol() };
+	Ok(Some((next_tok, TokenInfo { doc: tok.take_doc(), src: src })))
+}
+
 impl <Iter: Iterator<Item=char>> ParserState<Iter> {
 	pub fn new(tok: Tokeniser<Iter>, ids: &mut IdStringDb) -> Result<ParserState<Iter>, ParserError> {
+		ParserState::with_include_paths(tok, ids, Vec::new())
+	}
+	// Include paths are searched, in order, after the directory of the including file
+	pub fn with_include_paths(tok: Tokeniser<Iter>, ids: &mut IdStringDb, include_paths: Vec<PathBuf>) -> Result<ParserState<Iter>, ParserError> {
+		let root_path = PathBuf::from(ids.get_str(tok.file()));
+		let root_path = fs::canonicalize(&root_path).unwrap_or(root_path);
 		let mut p = ParserState {
 			tokeniser: tok,
 			toks: VecDeque::new(),
+			info: VecDeque::new(),
 			ptr: 0,
+			popped_end: LineCol { line: 1, col: 1 },
 			ambig_stack: Vec::new(),
+			includes: Vec::new(),
+			inputs: VecDeque::new(),
+			included: FxHashSet::default(),
+			include_paths: include_paths,
+			root_path: root_path.clone(),
+			root_done: false,
+			pending_doc: None,
 		};
+		p.included.insert(root_path);
 		p.update_lookahead(ids, 1)?;
 		Ok(p)
 	}
+	// Add another top level file, parsed as if it followed the main one
+	pub fn add_input(&mut self, path: PathBuf) {
+		self.inputs.push_back(path);
+	}
+	fn next_raw_token(&mut self, ids: &mut IdStringDb) -> Result<Option<(Token, TokenInfo)>, ParserError> {
+		match self.includes.last_mut() {
+			Some((tok, _)) => read_token(tok, ids),
+			None => read_token(&mut self.tokeniser, ids),
+		}
+	}
 	pub fn update_lookahead(&mut self, ids: &mut IdStringDb, n: usize) -> Result<(), ParserError> {
-		while self.toks.len() < (self.ptr + n) && !self.tokeniser.eof() {
-			let next_tok = self.tokeniser.token(ids).or_else(|e| Err(ParserError::from_tok(e)))?;
-			self.toks.push_back((next_tok, self.tokeniser.linecol()));
+		while self.toks.len() < (self.ptr + n) {
+			let (next_tok, info) = match self.next_raw_token(ids)? {
+				Some(next) => next,
+				None => {
+					// carry on with whatever included the file that just ended; or the next input
+					if self.includes.pop().is_none() {
+						self.root_done = true;
+						match self.inputs.pop_front() {
+							Some(path) => {
+								let src = self.src();
+								self.open_file(ids, path, src)?
+							},
+							None => break,
+						}
+					}
+					continue;
+				}
+			};
+			if next_tok == Symbol("#") || next_tok == Keyword(constids::import) {
+				if info.doc.is_some() {
+					self.pending_doc = info.doc;
+				}
+				self.include_directive(ids, next_tok == Symbol("#"), info.src)?;
+				continue;
+			}
+			let pending_doc = self.pending_doc.take();
+			let info = match info.doc {
+				Some(_) => info,
+				None => TokenInfo { doc: pending_doc, src: info.src },
+			};
+			self.toks.push_back((next_tok, info.src.start));
+			self.info.push_back(info);
 		}
 		Ok(())
 	}
+	// #include "file" or import "file"; errors are reported at the start of the directive, given by src
+	fn include_directive(&mut self, ids: &mut IdStringDb, is_hash: bool, src: SrcInfo) -> Result<(), ParserError> {
+		if is_hash {
+			match self.next_raw_token(ids)? {
+				Some((Ident(x), ..)) if x == constids::include => {},
+				_ => return Err(ParserError::at(src, format!("expected 'include' after '#'"))),
+			}
+		}
+		let name = match self.next_raw_token(ids)? {
+			Some((StrLiteral(name), ..)) => name,
+			_ => return Err(ParserError::at(src, format!("expected file name in quotes"))),
+		};
+		if !is_hash {
+			match self.next_raw_token(ids)? {
+				Some((Symbol(";"), ..)) => {},
+				_ => return Err(ParserError::at(src, format!("expected ';' after import"))),
+			}
+		}
+		// relative to the including file first, then the include paths
+		let curr_file = PathBuf::from(ids.get_str(self.curr_file()));
+		let curr_dir = curr_file.parent().map(|d| d.to_path_buf()).unwrap_or_default();
+		let path = once(&curr_dir).chain(self.include_paths.iter()).map(|d| d.join(&name)).find(|p| p.is_file())
+			.ok_or_else(|| ParserError::at(src, format!("unable to find included file \"{}\"", name)))?;
+		self.open_file(ids, path, src)
+	}
+	// Start reading a file; src is what caused it to be read, for errors
+	fn open_file(&mut self, ids: &mut IdStringDb, path: PathBuf, src: SrcInfo) -> Result<(), ParserError> {
+		let canon = fs::canonicalize(&path).unwrap_or(path.clone());
+		let mut active: Vec<&PathBuf> = self.includes.iter().map(|(_, p)| p).collect();
+		if !self.root_done {
+			active.insert(0, &self.root_path);
+		}
+		if let Some(pos) = active.iter().position(|p| **p == canon) {
+			let cycle: Vec<String> = active[pos..].iter().map(|p| p.display().to_string()).chain(once(canon.display().to_string())).collect();
+			return Err(ParserError::at(src, format!("include cycle: {}", cycle.join(" -> "))));
+		}
+		if !self.included.insert(canon.clone()) {
+			return Ok(());
+		}
+		let text = fs::read_to_string(&path).map_err(|e| ParserError::at(src, format!("unable to read {}: {}", path.display(), e)))?;
+		let tok = Tokeniser::new(ids.id(&path.to_string_lossy()), text.chars().collect::<Vec<_>>().into_iter());
+		self.includes.push((tok, canon));
+		Ok(())
+	}
+	fn curr_file(&self) -> IdString {
+		match self.includes.last() {
+			Some((tok, _)) => tok.file(),
+			None => self.tokeniser.file(),
+		}
+	}
 	pub fn peek(&self) -> Option<&(Token, LineCol)> {
 		self.toks.get(self.ptr)
 	}
-	pub fn err(&self, msg: String) -> ParserError {
-		ParserError {
-			file: self.tokeniser.file(),
-			lc: self.tokeniser.linecol(),
-			msg: msg,
+	// Doc comments before the next token
+	pub fn doc_comment(&self) -> Option<String> {
+		self.info.get(self.ptr).and_then(|i| i.doc.clone())
+	}
+	// Where the next token is; or the end of the file if there are none left
+	pub fn src(&self) -> SrcInfo {
+		match self.info.get(self.ptr) {
+			Some(info) => info.src,
+			None => {
+				let lc = match self.includes.last() {
+					Some((tok, _)) => tok.linecol(),
+					None => self.tokeniser.linecol(),
+				};
+				SrcInfo { file: self.curr_file(), start: lc, end: lc }
+			},
 		}
 	}
+	// From the start of some earlier location, to the end of the last token consumed
+	pub fn span(&self, from: SrcInfo) -> SrcInfo {
+		let end = match self.ptr {
+			0 => self.popped_end,
+			p => self.info[p - 1].src.end,
+		};
+		SrcInfo { file: from.file, start: from.start, end: max(end, from.start) }
+	}
+	pub fn err(&self, msg: String) -> ParserError {
+		ParserError::at(self.src(), msg)
+	}
 	pub fn get(&mut self, ids: &mut IdStringDb) -> Result<(Token, LineCol), ParserError> {
 		self.update_lookahead(ids, 2)?;
 		if !self.ambig_stack.is_empty() {
@@ -64,6 +236,9 @@ impl <Iter: Iterator<Item=char>> ParserState<Iter> {
 			self.ptr += 1;
 			Ok(t.clone())
 		} else {
+			if let Some(info) = self.info.pop_front() {
+				self.popped_end = info.src.end;
+			}
 			self.toks.pop_front().ok_or(self.err(format!("unexpected end of file")))
 		}
 	}
@@ -76,6 +251,9 @@ impl <Iter: Iterator<Item=char>> ParserState<Iter> {
 		let next_ptr = self.ambig_stack.pop().unwrap();
 		for _ in next_ptr..self.ptr {
 			self.toks.pop_front();
+			if let Some(info) = self.info.pop_front() {
+				self.popped_end = info.src.end;
+			}
 		}
 		self.ptr = next_ptr;
 		self.update_lookahead(ids, 1)?;
@@ -234,4 +412,55 @@ mod test {
 		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("bar")));
 		Ok(())
 	}
+	#[test]
+	fn includes() -> Result<(), ParserError> {
+		let dir = std::env::temp_dir().join(format!("meowality_includes_{}", std::process::id()));
+		fs::create_dir_all(dir.join("inc")).unwrap();
+		fs::write(dir.join("a.hls"), "#include \"b.hls\" a").unwrap();
+		fs::write(dir.join("inc/b.hls"), "b").unwrap();
+		fs::write(dir.join("c.hls"), "#include \"d.hls\"").unwrap();
+		fs::write(dir.join("d.hls"), "import \"c.hls\";").unwrap();
+		fs::write(dir.join("e.hls"), "e").unwrap();
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let top = dir.join("top.hls");
+		let tok = Tokeniser::new(ids.id(&top.to_string_lossy()), "import \"a.hls\"; #include \"a.hls\" x".chars());
+		let mut ps = ParserState::with_include_paths(tok, &mut ids, vec![dir.join("inc")])?;
+		ps.add_input(dir.join("e.hls"));
+		// included files are only read once, and searched for in the include paths
+		assert!(ids.get_str(ps.src().file).ends_with("b.hls"));
+		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("b")));
+		assert!(ids.get_str(ps.src().file).ends_with("a.hls"));
+		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("a")));
+		assert_eq!(ps.src().file, ids.id(&top.to_string_lossy()));
+		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("x")));
+		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("e")));
+		let tok = Tokeniser::new(ids.id(&dir.join("c.hls").to_string_lossy()), "#include \"d.hls\"".chars());
+		match ParserState::new(tok, &mut ids) {
+			Err(e) => assert!(e.msg.contains("include cycle") && e.msg.ends_with("c.hls")),
+			Ok(_) => panic!("expected an error"),
+		}
+		// errors point at the start of the directive rather than where the tokeniser got to
+		let at = |src: &str, ids: &mut IdStringDb| {
+			let tok = Tokeniser::new(ids.id(&top.to_string_lossy()), src.chars().collect::<Vec<_>>().into_iter());
+			let mut ps = ParserState::new(tok, ids).unwrap();
+			let e = ps.consume_ident(ids).unwrap_err();
+			(e.lc.line, e.lc.col, e.msg)
+		};
+		assert_eq!(at("x\n  #inclde \"a.hls\"", &mut ids), (2, 3, format!("expected 'include' after '#'")));
+		assert_eq!(at("x\n  import a.hls;", &mut ids), (2, 3, format!("expected file name in quotes")));
+		assert_eq!(at("x\n  #include \"missing.hls\"\n", &mut ids), (2, 3, format!("unable to find included file \"missing.hls\"")));
+		// doc comments before a directive are kept for the next token
+		let tok = Tokeniser::new(ids.id(&top.to_string_lossy()), "/// about x\n#include \"e.hls\"\nx /// about y\nimport \"e.hls\";\n/// own doc\ny".chars().collect::<Vec<_>>().into_iter());
+		let mut ps = ParserState::new(tok, &mut ids)?;
+		assert_eq!(ps.doc_comment(), Some(format!("about x")));
+		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("e")));
+		assert_eq!(ps.doc_comment(), None);
+		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("x")));
+		assert_eq!(ps.doc_comment(), Some(format!("own doc")));
+		assert_eq!(ps.consume_ident(&mut ids)?, Some(ids.id("y")));
+		assert_eq!(ps.doc_comment(), None);
+		fs::remove_dir_all(&dir).unwrap();
+		Ok(())
+	}
 }
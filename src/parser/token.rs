@@ -38,6 +38,8 @@ pub const SYMBOLS : &[&'static str] = &[
 	")",
 	",",
 	";",
+	":",
+	"?",
 	".",
 	"+",
 	"-",
@@ -50,6 +52,7 @@ pub const SYMBOLS : &[&'static str] = &[
 	"=",
 	"~",
 	"!",
+	"#",
 ];
 
 // A list of IdStrings that are _always_ parsed as keywords and not identifiers
@@ -76,6 +79,10 @@ pub const KEYWORDS : &[IdString] = &[
 	constids::r#else,
 	constids::r#for,
 	constids::r#while,
+	constids::r#do,
+	constids::r#switch,
+	constids::r#case,
+	constids::default,
 	constids::multicycle,
 	constids::meta,
 	constids::r#break,
@@ -84,8 +91,10 @@ pub const KEYWORDS : &[IdString] = &[
 	constids::r#sizeof,
 	constids::block,
 	constids::static_cast,
+	constids::static_assert,
 	constids::r#const,
 	constids::r#static,
+	constids::import,
 ];
 
 #[derive(Eq, PartialEq, Clone)]
@@ -1,3 +1,4 @@
+use std::cmp::max;
 use std::collections::VecDeque;
 use crate::parser::token::*;
 use crate::ast::LineCol;
@@ -49,6 +50,8 @@ pub struct Tokeniser<Iter: Iterator<Item=char>> {
 	max_symbol_len: usize,
 	first_symbol_chars: CharPool,
 	buf: String,
+	// Doc comments since the last token, which document whatever follows them
+	doc: Option<String>,
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -69,6 +72,7 @@ impl <Iter: Iterator<Item=char>> Tokeniser<Iter> {
 			max_symbol_len: SYMBOLS.iter().map(|x| x.len()).max().unwrap_or(0),
 			first_symbol_chars: CharPool::from_iter(SYMBOLS.iter().map(|x| x.chars().nth(0).unwrap())),
 			buf: String::new(),
+			doc: None,
 		};
 		state.update_lookahead(1);
 		return state;
@@ -101,10 +105,75 @@ impl <Iter: Iterator<Item=char>> Tokeniser<Iter> {
 		let next = self.get();
 		assert_eq!(next, Some(ch));
 	}
-	pub fn skip_whitespace(&mut self) {
-		while self.peek().map(|x| x.is_whitespace()).unwrap_or(false) {
+	// Skip whitespace and comments, collecting any doc comments
+	pub fn skip_whitespace(&mut self) -> Result<(), TokeniserError> {
+		loop {
+			match self.peek() {
+				Some(c) if c.is_whitespace() => { self.get(); },
+				Some('/') => {
+					self.update_lookahead(2);
+					match self.lookahead.get(1) {
+						Some('/') => self.line_comment(),
+						Some('*') => self.block_comment()?,
+						_ => break,
+					}
+				},
+				_ => break,
+			}
+		}
+		Ok(())
+	}
+	// Line comments run to the end of the line; those starting with exactly three slashes are doc comments
+	fn line_comment(&mut self) {
+		self.assert_get('/');
+		self.assert_get('/');
+		self.update_lookahead(2);
+		let is_doc = self.peek() == Some('/') && self.lookahead.get(1) != Some(&'/');
+		if is_doc {
+			self.get();
+			if self.peek() == Some(' ') {
+				self.get();
+			}
+		}
+		let mut text = String::new();
+		while let Some(ch) = self.peek() {
+			if ch == '\n' {
+				break;
+			}
+			text.push(ch);
 			self.get();
 		}
+		if is_doc {
+			match &mut self.doc {
+				Some(doc) => { doc.push('\n'); doc.push_str(text.trim_end()); },
+				None => self.doc = Some(text.trim_end().to_string()),
+			}
+		}
+	}
+	// Block comments may be nested, so that code containing comments can be commented out
+	fn block_comment(&mut self) -> Result<(), TokeniserError> {
+		let start = self.linecol();
+		self.assert_get('/');
+		self.assert_get('*');
+		let mut depth = 1;
+		while depth > 0 {
+			self.update_lookahead(2);
+			match (self.peek(), self.lookahead.get(1)) {
+				(Some('*'), Some('/')) => { self.get(); self.get(); depth -= 1; },
+				(Some('/'), Some('*')) => { self.get(); self.get(); depth += 1; },
+				(Some(_), _) => { self.get(); },
+				(None, _) => return Err(TokeniserError {
+					file: self.file(),
+					lc: start,
+					msg: format!("unterminated block comment starting at line {}, column {}", start.line, start.col),
+				}),
+			}
+		}
+		Ok(())
+	}
+	// The doc comments before the token just read
+	pub fn take_doc(&mut self) -> Option<String> {
+		self.doc.take()
 	}
 	pub fn file(&self) -> IdString {
 		self.filename
@@ -153,6 +222,7 @@ impl <Iter: Iterator<Item=char>> Tokeniser<Iter> {
 					// TODO: hex and octal sequences
 					c => { return Err(self.err(format!("unexpected {} in escape sequence", c))); }
 				}
+				escaped = false;
 			} else {
 				if ch == str_type {
 					break;
@@ -165,40 +235,97 @@ impl <Iter: Iterator<Item=char>> Tokeniser<Iter> {
 		}
 		Ok(())
 	}
+	// Split a _u<N> or _s<N> size suffix off a numeric literal, returning the digits (still with separators) and the
+	// width and signedness if given
+	fn split_suffix(&self) -> Result<(&str, Option<(usize, bool)>), TokeniserError> {
+		if let Some(pos) = self.buf.rfind('_') {
+			let suffix = &self.buf[pos+1..];
+			let is_signed = suffix.starts_with('s');
+			if (is_signed || suffix.starts_with('u')) && suffix.len() > 1 && suffix[1..].chars().all(|c| c.is_ascii_digit()) {
+				let width = suffix[1..].parse::<usize>().or(Err(self.err(format!("invalid width in literal suffix '{}'", suffix))))?;
+				if width == 0 {
+					return Err(self.err(format!("literal suffix '{}' has zero width", suffix)));
+				}
+				return Ok((&self.buf[..pos], Some((width, is_signed))));
+			}
+		}
+		Ok((&self.buf, None))
+	}
 	pub fn parse_number(&self) -> Result<BitVector, TokeniserError> {
-		if self.buf.len() >= 2 && self.buf.starts_with('0') {
+		let (digits, suffix) = self.split_suffix()?;
+		let digits : Vec<char> = digits.chars().filter(|&c| c != '_').collect();
+		let is_based = digits.len() >= 2 && digits[0] == '0';
+		let mut bv = if is_based {
 			// based string, arbitrary precision
 			// NB unlike C cursedness, we use 0o and not 0 as a prefix for octal
-			let base_ch = self.buf.chars().nth(1).unwrap();
-			let base_l2 = match base_ch {
+			let base_l2 = match digits[1] {
 				'x' => Ok(4),
 				'b' => Ok(1),
 				'o' => Ok(3),
 				x => Err(self.err(format!("invalid base '{}', use 0o as a prefix for octal literals", x)))
 			}?;
 			let base = 1<<base_l2;
-			let mut bv = BitVector::new((self.buf.len() - 2) * base_l2, false);
-			for i in 0..(self.buf.len() - 2) {
-				let ch_idx = (self.buf.len() - 1) - i;
-				// This looks bad but we know there is no UTF-8 at this point
-				let ch = &self.buf[ch_idx..ch_idx+1];
-				// Is there a better way to do this???
-				let ch_value = u8::from_str_radix(ch, base).or(Err(self.err(format!("unexpected char '{}' in base-{} literal", ch, base))))?;
+			let mut bv = BitVector::new((digits.len() - 2) * base_l2, false);
+			for (i, &ch) in digits[2..].iter().rev().enumerate() {
+				// x, z and ? digits set every bit of the digit, for don't-care masks
+				let state = match ch {
+					'x' | 'X' => Some(State::Sx),
+					'z' | 'Z' | '?' => Some(State::Sz),
+					_ => None,
+				};
+				if let Some(state) = state {
+					for j in 0..base_l2 {
+						bv.set(i * base_l2 + j, state);
+					}
+					continue;
+				}
+				let ch_value = ch.to_digit(base).ok_or_else(|| self.err(format!("unexpected char '{}' in base-{} literal", ch, base)))?;
 				for j in 0..base_l2 {
 					if ((ch_value >> j) & 0x1) == 0x1 {
 						bv.set(i * base_l2 + j, State::S1);
 					}
 				}
 			}
-			Ok(bv)
+			bv
 		} else {
-			// decimal string
-			let parsed = self.buf.parse::<u64>().or(Err(self.err(format!("failed to parse integer literal '{}'", &self.buf))))?;
-			Ok(BitVector::from_u64(parsed, 64))
+			// decimal string, arbitrary precision; accumulated as 32-bit limbs, least significant first
+			let mut limbs : Vec<u64> = vec![0];
+			for &ch in digits.iter() {
+				let mut carry = ch.to_digit(10).ok_or_else(|| self.err(format!("failed to parse integer literal '{}'", &self.buf)))? as u64;
+				for l in limbs.iter_mut() {
+					let x = *l * 10 + carry;
+					*l = x & 0xFFFFFFFF;
+					carry = x >> 32;
+				}
+				if carry != 0 {
+					limbs.push(carry);
+				}
+			}
+			let bits = (0..limbs.len() * 32).rev().find(|&i| (limbs[i / 32] >> (i % 32)) & 0x1 == 0x1).map_or(0, |i| i + 1);
+			// unsized decimals stay 64 bits wide unless they need more
+			let mut bv = BitVector::new(max(bits, 64), false);
+			for i in 0..bits {
+				if (limbs[i / 32] >> (i % 32)) & 0x1 == 0x1 {
+					bv.set(i, State::S1);
+				}
+			}
+			bv
+		};
+		if let Some((width, is_signed)) = suffix {
+			if (width..bv.len()).any(|i| bv.get(i) != Some(State::S0)) {
+				return Err(self.err(format!("literal '{}' does not fit in {} bits", &self.buf, width)));
+			}
+			// a signed decimal is a magnitude, so must leave the sign bit clear; based literals give the bits directly
+			if is_signed && !is_based && bv.get(width - 1) != Some(State::S0) {
+				return Err(self.err(format!("literal '{}' does not fit in a signed {} bit value", &self.buf, width)));
+			}
+			bv = bv.resize(width, false);
+			bv.is_signed = is_signed;
 		}
+		Ok(bv)
 	}
 	pub fn token(&mut self, ids: &mut IdStringDb) -> Result<Token, TokeniserError> {
-		self.skip_whitespace();
+		self.skip_whitespace()?;
 		let ch0 = self.peek().ok_or(self.err(format!("end of file")))?;
 		if self.is_symbol_start(ch0) {
 			// parse as symbol token
@@ -217,9 +344,11 @@ impl <Iter: Iterator<Item=char>> Tokeniser<Iter> {
 			self.buf.clear();
 			while !self.eof() {
 				let ch = self.peek().unwrap();
-				if ch.is_ascii_alphanumeric() || ch == '_' || ch == '\'' {
-					// _ and ' are used as digit separators 
-					if ch != '_' && ch != '\'' {
+				// ? is a don't-care digit, only allowed in based literals
+				let is_based = self.buf.starts_with("0x") || self.buf.starts_with("0b");
+				if ch.is_ascii_alphanumeric() || ch == '_' || ch == '\'' || (ch == '?' && is_based) {
+					// _ and ' are used as digit separators; _ is kept for now as it also introduces size suffixes
+					if ch != '\'' {
 						self.buf.push(ch);
 					}
 					self.get();
@@ -233,9 +362,9 @@ impl <Iter: Iterator<Item=char>> Tokeniser<Iter> {
 			self.get_string('\"')?;
 			return Ok(Token::StrLiteral(self.buf.clone()));
 		} else if ch0 == '\'' {
-			// parse as char (or multi-char) literal
+			// parse as char literal
 			self.get_string('\'')?;
-			return Ok(Token::StrLiteral(self.buf.clone()));
+			return Ok(Token::ChrLiteral(self.buf.clone()));
 		} else {
 			// parse as identifier
 			self.buf.clear();
@@ -301,6 +430,63 @@ mod test {
 		assert!(tok.token(&mut ids).is_err());
 	}
 	#[test]
+	fn wide_and_sized_literals() {
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let mut tok = Tokeniser::new(ids.id("<test>"), "
+			340282366920938463463374607431768211457
+			0x1F_u5
+			-3_s8
+			1_000_u12
+			0b10x?
+			0x?F
+			0x1F_u4
+			127_s8
+			0x80_s8
+			128_s8
+			255_s8
+		".chars());
+		// 2^128 + 1
+		let mut big = BitVector::new(129, false);
+		big.set(0, State::S1);
+		big.set(128, State::S1);
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(big)));
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(BitVector::from_u64(0x1F, 5))));
+		assert_eq!(tok.token(&mut ids), Ok(Symbol("-")));
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(BitVector::from_i64(3, 8))));
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(BitVector::from_u64(1000, 12))));
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(BitVector::from_str("10xz"))));
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(BitVector::from_str("zzzz1111"))));
+		assert!(tok.token(&mut ids).unwrap_err().msg.contains("does not fit in 4 bits"));
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(BitVector::from_i64(127, 8))));
+		let mut min = BitVector::from_u64(0x80, 8);
+		min.is_signed = true;
+		assert_eq!(tok.token(&mut ids), Ok(IntLiteral(min)));
+		assert!(tok.token(&mut ids).unwrap_err().msg.contains("does not fit in a signed 8 bit value"));
+		assert!(tok.token(&mut ids).unwrap_err().msg.contains("does not fit in a signed 8 bit value"));
+	}
+	#[test]
+	fn comments() {
+		let mut ids = IdStringDb::new();
+		constids::do_ids_init(&mut ids);
+		let mut tok = Tokeniser::new(ids.id("<test>"), "a // line
+			/* block /* nested */ still comment */ b / c //// not doc
+			/// doc one
+			///doc two
+			d /* a".chars());
+		assert_eq!(tok.token(&mut ids), Ok(Ident(ids.id("a"))));
+		assert_eq!(tok.token(&mut ids), Ok(Ident(ids.id("b"))));
+		assert_eq!(tok.token(&mut ids), Ok(Symbol("/")));
+		assert_eq!(tok.token(&mut ids), Ok(Ident(ids.id("c"))));
+		assert_eq!(tok.take_doc(), None);
+		assert_eq!(tok.token(&mut ids), Ok(Ident(ids.id("d"))));
+		assert_eq!(tok.take_doc(), Some("doc one\ndoc two".to_string()));
+		// reported where the comment starts
+		let err = tok.token(&mut ids).unwrap_err();
+		assert_eq!(err.lc, LineCol { line: 5, col: 6 });
+		assert!(err.msg.contains("unterminated block comment"));
+	}
+	#[test]
 	fn keywords() {
 		let mut ids = IdStringDb::new();
 		constids::do_ids_init(&mut ids);